@@ -24,6 +24,15 @@ fn criterion_benchmark(c: &mut Criterion) {
         let right: Vec<u8> = vec![0; 1000];
         d.iter(|| ::wu_diff::diff(&left, &right));
     });
+
+    c.bench_function("200 non equal lines in 1000 interned", |d| {
+        let line = "x".repeat(200);
+        let left: Vec<String> = (0..1000)
+            .map(|i| if i % 5 == 0 { format!("{}{}", line, i) } else { line.clone() })
+            .collect();
+        let right: Vec<String> = vec![line; 1000];
+        d.iter(|| ::wu_diff::diff_interned(&left, &right));
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,78 @@
+use std::hash::Hash;
+
+use histogram::histogram;
+use intern::Interner;
+use patience::patience;
+use {diff_by, DiffElement, DiffResult};
+
+/// Selects the algorithm used by `diff_with_algorithm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// The O(NP) algorithm used by `diff`. Always yields a shortest edit script.
+    #[default]
+    Wu,
+    /// Anchors on elements which occur exactly once in both inputs and fills the
+    /// gaps between them with the O(NP) algorithm. The script may be longer than
+    /// the shortest one, but rarely aligns on noise such as blank lines or `}`.
+    Patience,
+    /// Splits the inputs around the longest common run containing the elements
+    /// that occur least often, as git does with `--histogram`. Regions without
+    /// such an anchor are diffed with the O(NP) algorithm.
+    Histogram,
+}
+
+/// Same as `diff`, but computes the script with the given `algorithm`.
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated.
+pub fn diff_with_algorithm<T: Hash + Eq>(old: &[T], new: &[T], algorithm: Algorithm) -> Vec<DiffResult> {
+    match algorithm {
+        Algorithm::Wu => diff_by(old, new, PartialEq::eq),
+        Algorithm::Patience => {
+            let mut interner = Interner::new();
+            let old_tokens = interner.intern(old);
+            let new_tokens = interner.intern(new);
+            patience(&old_tokens, &new_tokens)
+        }
+        Algorithm::Histogram => {
+            let mut interner = Interner::new();
+            let old_tokens = interner.intern(old);
+            let new_tokens = interner.intern(new);
+            histogram(&old_tokens, &new_tokens)
+        }
+    }
+}
+
+// Appends `results`, computed on sub slices starting at `old_offset` and
+// `new_offset`, to `dest` with their indices moved into the enclosing slices.
+pub(crate) fn extend_shifted(dest: &mut Vec<DiffResult>, results: Vec<DiffResult>, old_offset: usize, new_offset: usize) {
+    let shift = |e: DiffElement| DiffElement {
+        old_index: e.old_index.map(|i| i + old_offset),
+        new_index: e.new_index.map(|i| i + new_offset),
+    };
+    dest.extend(results.into_iter().map(|r| match r {
+        DiffResult::Removed(e) => DiffResult::Removed(shift(e)),
+        DiffResult::Common(e) => DiffResult::Common(shift(e)),
+        DiffResult::Added(e) => DiffResult::Added(shift(e)),
+    }));
+}
+
+pub(crate) fn push_common(result: &mut Vec<DiffResult>, old_index: usize, new_index: usize, len: usize) {
+    result.extend((0..len).map(|i| {
+        DiffResult::Common(DiffElement {
+            old_index: Some(old_index + i),
+            new_index: Some(new_index + i),
+        })
+    }));
+}
+
+#[test]
+fn should_use_wu_by_default() {
+    let old = vec!["a", "b", "c"];
+    let new = vec!["a", "c", "d"];
+    assert_eq!(
+        diff_with_algorithm(&old, &new, Algorithm::default()),
+        ::diff(&old, &new)
+    );
+}
@@ -0,0 +1,397 @@
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use hunk::hunks;
+use ops::group_ops;
+use unified::{unified_range, write_to_string};
+use {diff, DiffOp, DiffResult};
+
+/// A terminal color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 basic colors, `0..=15`: `0..=7` are the normal colors and
+    /// `8..=15` their bright variants. Values above 15 are clamped to a bright
+    /// color by keeping their lowest three bits, so `Ansi(17)` renders as
+    /// `Ansi(9)`; use `Fixed` for the rest of the 256-color palette.
+    Ansi(u8),
+    /// An index into the 256-color palette.
+    Fixed(u8),
+    /// A 24-bit truecolor value.
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn code(&self, background: bool) -> String {
+        match *self {
+            Color::Ansi(n) if n < 8 => format!("{}", if background { 40 } else { 30 } + u32::from(n)),
+            Color::Ansi(n) => format!("{}", if background { 100 } else { 90 } + u32::from(n & 7)),
+            Color::Fixed(n) => format!("{};5;{}", if background { 48 } else { 38 }, n),
+            Color::Rgb(r, g, b) => format!("{};2;{};{};{}", if background { 48 } else { 38 }, r, g, b),
+        }
+    }
+}
+
+/// Foreground and background colors applied to a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+}
+
+impl Style {
+    fn escape(&self) -> String {
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        codes.extend(self.fg.map(|c| c.code(false)));
+        codes.extend(self.bg.map(|c| c.code(true)));
+        format!("\x1b[{}m", codes.join(";"))
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+
+/// Styles used by `write_colored`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub removed: Style,
+    pub added: Style,
+    /// Applied to the changed spans of removed lines when intra-line
+    /// highlighting is enabled.
+    pub removed_highlight: Style,
+    /// Same as `removed_highlight`, for added lines.
+    pub added_highlight: Style,
+    /// Applied to `@@` hunk headers.
+    pub header: Style,
+}
+
+impl Theme {
+    /// Red and green from the 16 basic colors, supported by every terminal.
+    pub fn basic() -> Self {
+        Theme {
+            removed: Style {
+                fg: Some(Color::Ansi(1)),
+                ..Style::default()
+            },
+            added: Style {
+                fg: Some(Color::Ansi(2)),
+                ..Style::default()
+            },
+            removed_highlight: Style {
+                fg: Some(Color::Ansi(15)),
+                bg: Some(Color::Ansi(1)),
+                bold: true,
+            },
+            added_highlight: Style {
+                fg: Some(Color::Ansi(0)),
+                bg: Some(Color::Ansi(2)),
+                bold: true,
+            },
+            header: Style {
+                fg: Some(Color::Ansi(6)),
+                ..Style::default()
+            },
+        }
+    }
+
+    /// Softer colors from the 256-color palette.
+    pub fn ansi256() -> Self {
+        Theme {
+            removed: Style {
+                fg: Some(Color::Fixed(203)),
+                ..Style::default()
+            },
+            added: Style {
+                fg: Some(Color::Fixed(114)),
+                ..Style::default()
+            },
+            removed_highlight: Style {
+                fg: Some(Color::Fixed(203)),
+                bg: Some(Color::Fixed(52)),
+                bold: false,
+            },
+            added_highlight: Style {
+                fg: Some(Color::Fixed(114)),
+                bg: Some(Color::Fixed(22)),
+                bold: false,
+            },
+            header: Style {
+                fg: Some(Color::Fixed(74)),
+                ..Style::default()
+            },
+        }
+    }
+
+    /// Same as `ansi256`, in 24-bit colors.
+    pub fn truecolor() -> Self {
+        Theme {
+            removed: Style {
+                fg: Some(Color::Rgb(0xf0, 0x71, 0x78)),
+                ..Style::default()
+            },
+            added: Style {
+                fg: Some(Color::Rgb(0x8e, 0xc0, 0x7c)),
+                ..Style::default()
+            },
+            removed_highlight: Style {
+                fg: Some(Color::Rgb(0xf0, 0x71, 0x78)),
+                bg: Some(Color::Rgb(0x5a, 0x1e, 0x1e)),
+                bold: false,
+            },
+            added_highlight: Style {
+                fg: Some(Color::Rgb(0x8e, 0xc0, 0x7c)),
+                bg: Some(Color::Rgb(0x1e, 0x4a, 0x1e)),
+                bold: false,
+            },
+            header: Style {
+                fg: Some(Color::Rgb(0x61, 0xaf, 0xef)),
+                ..Style::default()
+            },
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::basic()
+    }
+}
+
+/// Whether `write_colored` emits escape sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    /// Colors unless the `NO_COLOR` environment variable is set to a non-empty
+    /// value. Terminal detection is left to the caller.
+    #[default]
+    Auto,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => no_color_allows(env::var_os("NO_COLOR").as_deref()),
+        }
+    }
+}
+
+// Whether the value of `NO_COLOR` lets `ColorChoice::Auto` emit colors.
+fn no_color_allows(value: Option<&OsStr>) -> bool {
+    value.map_or(true, |v| v.is_empty())
+}
+
+/// Options used by `write_colored`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ColorFormat {
+    pub theme: Theme,
+    pub choice: ColorChoice,
+    /// Prints only hunks with this many common lines of context, each behind a
+    /// `@@` header. Every line is printed when `None`.
+    pub context: Option<usize>,
+    /// Highlights the changed characters of paired removed and added lines.
+    pub highlight_changes: bool,
+}
+
+struct Painter<'a, W: 'a> {
+    out: &'a mut W,
+    colored: bool,
+}
+
+impl<'a, W: Write> Painter<'a, W> {
+    fn paint(&mut self, style: &Style, text: &str) -> io::Result<()> {
+        if self.colored && !text.is_empty() && *style != Style::default() {
+            write!(self.out, "{}{}{}", style.escape(), text, RESET)
+        } else {
+            self.out.write_all(text.as_bytes())
+        }
+    }
+
+    // Writes `line` behind `prefix`, with the char ranges in `highlights`
+    // painted with `highlight`.
+    fn line(
+        &mut self,
+        prefix: &str,
+        line: &str,
+        style: &Style,
+        highlight: &Style,
+        highlights: &[Range<usize>],
+    ) -> io::Result<()> {
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        self.paint(style, prefix)?;
+        let mut pos = 0;
+        let chars: Vec<(usize, char)> = content.char_indices().collect();
+        let byte = |i: usize| chars.get(i).map_or(content.len(), |c| c.0);
+        for span in highlights {
+            self.paint(style, &content[byte(pos)..byte(span.start)])?;
+            self.paint(highlight, &content[byte(span.start)..byte(span.end)])?;
+            pos = span.end;
+        }
+        self.paint(style, &content[byte(pos)..])?;
+        self.out.write_all(b"\n")?;
+        if !line.ends_with('\n') {
+            self.out.write_all(b"\\ No newline at end of file\n")?;
+        }
+        Ok(())
+    }
+}
+
+// Returns the changed char ranges of `old` and of `new`.
+fn changed_spans(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old_chars: Vec<char> = old.trim_end_matches('\n').chars().collect();
+    let new_chars: Vec<char> = new.trim_end_matches('\n').chars().collect();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for op in group_ops(&diff(&old_chars, &new_chars)) {
+        let (o, n) = (op.old_range(), op.new_range());
+        if let DiffOp::Equal { .. } = op {
+            continue;
+        }
+        if !o.is_empty() {
+            removed.push(o);
+        }
+        if !n.is_empty() {
+            added.push(n);
+        }
+    }
+    (removed, added)
+}
+
+fn write_ops<W: Write>(
+    painter: &mut Painter<W>,
+    old: &[&str],
+    new: &[&str],
+    ops: &[DiffOp],
+    format: &ColorFormat,
+) -> io::Result<()> {
+    let theme = &format.theme;
+    let plain = Style::default();
+    for op in ops {
+        if let DiffOp::Equal { old: ref range, .. } = *op {
+            for line in &old[range.clone()] {
+                painter.line(" ", line, &plain, &plain, &[])?;
+            }
+            continue;
+        }
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        let paired = if format.highlight_changes {
+            old_range.len().min(new_range.len())
+        } else {
+            0
+        };
+        let spans: Vec<_> = (0..paired)
+            .map(|i| changed_spans(old[old_range.start + i], new[new_range.start + i]))
+            .collect();
+        for (i, line) in old[old_range].iter().enumerate() {
+            let highlights = spans.get(i).map_or(&[][..], |s| &s.0[..]);
+            painter.line("-", line, &theme.removed, &theme.removed_highlight, highlights)?;
+        }
+        for (i, line) in new[new_range].iter().enumerate() {
+            let highlights = spans.get(i).map_or(&[][..], |s| &s.1[..]);
+            painter.line("+", line, &theme.added, &theme.added_highlight, highlights)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, with removed
+/// lines in red and added lines in green, using ANSI escape sequences.
+///
+/// Lines are expected to keep their terminators, see `split_lines`.
+pub fn write_colored<W: Write>(
+    out: &mut W,
+    old: &[&str],
+    new: &[&str],
+    results: &[DiffResult],
+    format: &ColorFormat,
+) -> io::Result<()> {
+    let mut painter = Painter {
+        out,
+        colored: format.choice.enabled(),
+    };
+    match format.context {
+        None => write_ops(&mut painter, old, new, &group_ops(results), format),
+        Some(context) => {
+            for hunk in hunks(results, context) {
+                let header = format!(
+                    "@@ -{} +{} @@",
+                    unified_range(hunk.old_start, hunk.old_len),
+                    unified_range(hunk.new_start, hunk.new_len)
+                );
+                painter.paint(&format.theme.header, &header)?;
+                painter.out.write_all(b"\n")?;
+                write_ops(&mut painter, old, new, &hunk.ops, format)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Same as `write_colored`, but returns the text as a `String`.
+pub fn colored_diff(old: &[&str], new: &[&str], results: &[DiffResult], format: &ColorFormat) -> String {
+    write_to_string(|out| write_colored(out, old, new, results, format))
+}
+
+#[test]
+fn should_color_removed_and_added_lines() {
+    let old = vec!["a\n", "b\n"];
+    let new = vec!["a\n", "c\n"];
+    let format = ColorFormat {
+        choice: ColorChoice::Always,
+        ..ColorFormat::default()
+    };
+    let text = colored_diff(&old, &new, &diff(&old, &new), &format);
+    assert_eq!(
+        text,
+        " a\n\x1b[31m-\x1b[0m\x1b[31mb\x1b[0m\n\x1b[32m+\x1b[0m\x1b[32mc\x1b[0m\n"
+    );
+}
+
+#[test]
+fn should_highlight_changed_spans() {
+    let old = vec!["let a = 1;\n"];
+    let new = vec!["let b = 1;\n"];
+    let format = ColorFormat {
+        theme: Theme::ansi256(),
+        choice: ColorChoice::Always,
+        context: Some(3),
+        highlight_changes: true,
+    };
+    let text = colored_diff(&old, &new, &diff(&old, &new), &format);
+    assert_eq!(
+        text,
+        "\x1b[38;5;74m@@ -1 +1 @@\x1b[0m\n\
+         \x1b[38;5;203m-\x1b[0m\x1b[38;5;203mlet \x1b[0m\x1b[38;5;203;48;5;52ma\x1b[0m\x1b[38;5;203m = 1;\x1b[0m\n\
+         \x1b[38;5;114m+\x1b[0m\x1b[38;5;114mlet \x1b[0m\x1b[38;5;114;48;5;22mb\x1b[0m\x1b[38;5;114m = 1;\x1b[0m\n"
+    );
+}
+
+#[test]
+fn should_clamp_ansi_colors_above_15() {
+    assert_eq!(Color::Ansi(9).code(false), "91");
+    assert_eq!(Color::Ansi(17).code(false), "91");
+    assert_eq!(Color::Ansi(255).code(true), "107");
+}
+
+#[test]
+fn should_not_color_when_disabled() {
+    let old = vec!["a\n"];
+    let new = vec!["b\n"];
+    let format = ColorFormat {
+        choice: ColorChoice::Never,
+        ..ColorFormat::default()
+    };
+    assert_eq!(colored_diff(&old, &new, &diff(&old, &new), &format), "-a\n+b\n");
+}
+
+#[test]
+fn should_honor_no_color() {
+    assert!(no_color_allows(None));
+    assert!(no_color_allows(Some(OsStr::new(""))));
+    assert!(!no_color_allows(Some(OsStr::new("1"))));
+}
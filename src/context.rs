@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+
+use hunk::hunks;
+use unified::{write_line, write_to_string};
+use {DiffOp, DiffResult};
+
+/// Labels and context radius used by `write_context`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextFormat<'a> {
+    /// Written after `*** `.
+    pub old_label: &'a str,
+    /// Written after `--- `.
+    pub new_label: &'a str,
+    /// Number of common lines shown around each change.
+    pub context: usize,
+}
+
+impl<'a> Default for ContextFormat<'a> {
+    fn default() -> Self {
+        ContextFormat {
+            old_label: "a",
+            new_label: "b",
+            context: 3,
+        }
+    }
+}
+
+// Formats a hunk range the way GNU diff does in context format: `first,last`,
+// or a single number for one line or for the line before an empty range.
+fn context_range(start: usize, len: usize) -> String {
+    if len <= 1 {
+        format!("{}", start + len)
+    } else {
+        format!("{},{}", start + 1, start + len)
+    }
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, in context
+/// format, as GNU `diff -c` does.
+///
+/// Adjacent removed and added runs are paired into `!` changes. Lines are
+/// expected to keep their terminators, see `split_lines`. Nothing is written
+/// when there are no changes.
+pub fn write_context<W: Write>(
+    out: &mut W,
+    old: &[&str],
+    new: &[&str],
+    results: &[DiffResult],
+    format: &ContextFormat,
+) -> io::Result<()> {
+    let hunks = hunks(results, format.context);
+    if hunks.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "*** {}", format.old_label)?;
+    writeln!(out, "--- {}", format.new_label)?;
+    for hunk in hunks {
+        out.write_all(b"***************\n")?;
+
+        writeln!(out, "*** {} ****", context_range(hunk.old_start, hunk.old_len))?;
+        let has_removed = hunk
+            .ops
+            .iter()
+            .any(|op| matches!(*op, DiffOp::Delete { .. } | DiffOp::Replace { .. }));
+        if has_removed {
+            for op in &hunk.ops {
+                let prefix = match *op {
+                    DiffOp::Equal { .. } => "  ",
+                    DiffOp::Delete { .. } => "- ",
+                    DiffOp::Replace { .. } => "! ",
+                    DiffOp::Insert { .. } => continue,
+                };
+                for line in &old[op.old_range()] {
+                    write_line(out, prefix, line)?;
+                }
+            }
+        }
+
+        writeln!(out, "--- {} ----", context_range(hunk.new_start, hunk.new_len))?;
+        let has_added = hunk
+            .ops
+            .iter()
+            .any(|op| matches!(*op, DiffOp::Insert { .. } | DiffOp::Replace { .. }));
+        if has_added {
+            for op in &hunk.ops {
+                let prefix = match *op {
+                    DiffOp::Equal { .. } => "  ",
+                    DiffOp::Insert { .. } => "+ ",
+                    DiffOp::Replace { .. } => "! ",
+                    DiffOp::Delete { .. } => continue,
+                };
+                for line in &new[op.new_range()] {
+                    write_line(out, prefix, line)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write_context`, but returns the text as a `String`.
+pub fn context_diff(old: &[&str], new: &[&str], results: &[DiffResult], format: &ContextFormat) -> String {
+    write_to_string(|out| write_context(out, old, new, results, format))
+}
+
+#[test]
+fn should_format_context_diff() {
+    let old = ::split_lines("a\nb\nc\nd\n");
+    let new = ::split_lines("a\nx\nc\nd\ne\n");
+    let format = ContextFormat {
+        old_label: "old.txt",
+        new_label: "new.txt",
+        context: 1,
+    };
+    let text = context_diff(&old, &new, &::diff(&old, &new), &format);
+    assert_eq!(
+        text,
+        "*** old.txt\n--- new.txt\n***************\n*** 1,4 ****\n  a\n! b\n  c\n  d\n\
+         --- 1,5 ----\n  a\n! x\n  c\n  d\n+ e\n"
+    );
+}
+
+#[test]
+fn should_omit_unchanged_side() {
+    let old = ::split_lines("a\nb\n");
+    let new = ::split_lines("a\n");
+    let text = context_diff(&old, &new, &::diff(&old, &new), &ContextFormat::default());
+    assert_eq!(
+        text,
+        "*** a\n--- b\n***************\n*** 1,2 ****\n  a\n- b\n--- 1 ----\n"
+    );
+}
@@ -0,0 +1,226 @@
+use std::io::{self, Write};
+
+use unified::{unified_range, write_line, write_to_string};
+use {FilePatch, HunkLine, TextHunk};
+
+/// Tolerances used by `FilePatch::apply_fuzzy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzyOptions {
+    /// Largest distance, in lines, a hunk may be moved from its expected
+    /// position. `None` searches the whole file.
+    pub max_offset: Option<usize>,
+    /// Number of context lines that may be ignored at each end of a hunk, like
+    /// the `--fuzz` option of GNU `patch`.
+    pub fuzz: usize,
+}
+
+impl Default for FuzzyOptions {
+    fn default() -> Self {
+        FuzzyOptions {
+            max_offset: None,
+            fuzz: 2,
+        }
+    }
+}
+
+/// What `FilePatch::apply_fuzzy` did with a hunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkOutcome {
+    /// The hunk matched at its recorded position, ignoring `fuzz` context lines
+    /// at each end.
+    Applied { fuzz: usize },
+    /// The hunk matched `offset` lines away from its recorded position.
+    AppliedWithOffset { offset: isize, fuzz: usize },
+    /// The hunk matched nowhere and was skipped.
+    Rejected,
+}
+
+/// Result of `FilePatch::apply_fuzzy`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyApply<'b> {
+    /// The patched lines, without the changes of rejected hunks.
+    pub lines: Vec<&'b str>,
+    /// One outcome per hunk of the patch.
+    pub outcomes: Vec<HunkOutcome>,
+}
+
+impl<'b> FuzzyApply<'b> {
+    /// Whether every hunk was applied.
+    pub fn is_complete(&self) -> bool {
+        !self.outcomes.contains(&HunkOutcome::Rejected)
+    }
+}
+
+// Whether the context and removed lines of `lines` match `old` from `at`.
+fn matches_at(lines: &[HunkLine], old: &[&str], at: usize) -> bool {
+    let mut index = at;
+    for line in lines {
+        match *line {
+            HunkLine::Context(text) | HunkLine::Removed(text) => {
+                if old.get(index) != Some(&text) {
+                    return false;
+                }
+                index += 1;
+            }
+            HunkLine::Added(_) => (),
+        }
+    }
+    true
+}
+
+// Searches `old[min..]` for `lines`, closest to `expected` first.
+fn find(lines: &[HunkLine], old: &[&str], min: usize, expected: isize, max_offset: usize) -> Option<usize> {
+    let len = lines.iter().filter(|l| !matches!(**l, HunkLine::Added(_))).count();
+    if len > old.len() {
+        return None;
+    }
+    let (min, max) = (min as isize, (old.len() - len) as isize);
+    for delta in 0..=max_offset as isize {
+        let (after, before) = (expected + delta, expected - delta);
+        if after > max && before < min {
+            break;
+        }
+        for &at in &[after, before] {
+            if at >= min && at <= max && matches_at(lines, old, at as usize) {
+                return Some(at as usize);
+            }
+        }
+    }
+    None
+}
+
+impl<'a> FilePatch<'a> {
+    /// Applies the hunks to the lines `old` like GNU `patch`: a hunk that does
+    /// not match at its recorded position is searched for nearby, then retried
+    /// ignoring up to `options.fuzz` context lines at each end.
+    ///
+    /// Hunks that match nowhere are skipped and reported as `Rejected`, see
+    /// `write_rejects`.
+    pub fn apply_fuzzy<'b>(&'b self, old: &[&'b str], options: &FuzzyOptions) -> FuzzyApply<'b> {
+        let max_offset = options.max_offset.unwrap_or(old.len());
+        let mut lines = Vec::with_capacity(old.len());
+        let mut outcomes = Vec::with_capacity(self.hunks.len());
+        let mut index = 0;
+        let mut offset = 0;
+        for hunk in &self.hunks {
+            let leading = hunk.lines.iter().take_while(|l| matches!(**l, HunkLine::Context(_))).count();
+            let trailing = hunk.lines.iter().rev().take_while(|l| matches!(**l, HunkLine::Context(_))).count();
+            let mut outcome = HunkOutcome::Rejected;
+            for fuzz in 0..=options.fuzz {
+                let front = fuzz.min(leading);
+                let back = fuzz.min(trailing).min(hunk.lines.len() - front);
+                let trimmed = &hunk.lines[front..hunk.lines.len() - back];
+                let recorded = (hunk.old_start + front) as isize;
+                let at = match find(trimmed, old, index, recorded + offset, max_offset) {
+                    Some(at) => at,
+                    None => continue,
+                };
+                lines.extend_from_slice(&old[index..at]);
+                index = at;
+                for line in trimmed {
+                    match *line {
+                        HunkLine::Context(_) => {
+                            lines.push(old[index]);
+                            index += 1;
+                        }
+                        HunkLine::Removed(_) => index += 1,
+                        HunkLine::Added(text) => lines.push(text),
+                    }
+                }
+                offset = at as isize - recorded;
+                outcome = if offset == 0 {
+                    HunkOutcome::Applied { fuzz }
+                } else {
+                    HunkOutcome::AppliedWithOffset { offset, fuzz }
+                };
+                break;
+            }
+            outcomes.push(outcome);
+        }
+        lines.extend_from_slice(&old[index..]);
+        FuzzyApply { lines, outcomes }
+    }
+}
+
+fn write_hunk<W: Write>(out: &mut W, hunk: &TextHunk) -> io::Result<()> {
+    writeln!(
+        out,
+        "@@ -{} +{} @@",
+        unified_range(hunk.old_start, hunk.old_len),
+        unified_range(hunk.new_start, hunk.new_len)
+    )?;
+    for line in &hunk.lines {
+        match *line {
+            HunkLine::Context(text) => write_line(out, " ", text)?,
+            HunkLine::Removed(text) => write_line(out, "-", text)?,
+            HunkLine::Added(text) => write_line(out, "+", text)?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes the hunks of `patch` marked `Rejected` in `outcomes` as a unified
+/// diff, like the `.rej` files of GNU `patch`. Nothing is written when no hunk
+/// was rejected.
+pub fn write_rejects<W: Write>(out: &mut W, patch: &FilePatch, outcomes: &[HunkOutcome]) -> io::Result<()> {
+    let mut rejected = patch
+        .hunks
+        .iter()
+        .zip(outcomes)
+        .filter(|&(_, outcome)| *outcome == HunkOutcome::Rejected)
+        .peekable();
+    if rejected.peek().is_none() {
+        return Ok(());
+    }
+    writeln!(out, "--- {}", patch.old_path)?;
+    writeln!(out, "+++ {}", patch.new_path)?;
+    for (hunk, _) in rejected {
+        write_hunk(out, hunk)?;
+    }
+    Ok(())
+}
+
+/// Same as `write_rejects`, but returns the rejected hunks as a `String`.
+pub fn rejects(patch: &FilePatch, outcomes: &[HunkOutcome]) -> String {
+    write_to_string(|out| write_rejects(out, patch, outcomes))
+}
+
+#[test]
+fn should_apply_with_offset() {
+    let patch = ::parse_patch("--- a\n+++ b\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n").unwrap();
+    let old = ::split_lines("x\ny\na\nb\nc\n");
+    let result = patch[0].apply_fuzzy(&old, &FuzzyOptions::default());
+    assert_eq!(result.lines, ::split_lines("x\ny\na\nB\nc\n"));
+    assert_eq!(
+        result.outcomes,
+        vec![HunkOutcome::AppliedWithOffset { offset: 2, fuzz: 0 }]
+    );
+}
+
+#[test]
+fn should_apply_with_fuzz() {
+    let patch = ::parse_patch("--- a\n+++ b\n@@ -1,5 +1,5 @@\n a\n b\n-c\n+C\n d\n e\n").unwrap();
+    let old = ::split_lines("A\nb\nc\nd\nE\n");
+    let options = FuzzyOptions {
+        max_offset: Some(0),
+        fuzz: 0,
+    };
+    assert_eq!(patch[0].apply_fuzzy(&old, &options).outcomes, vec![HunkOutcome::Rejected]);
+    let result = patch[0].apply_fuzzy(&old, &FuzzyOptions::default());
+    assert_eq!(result.lines, ::split_lines("A\nb\nC\nd\nE\n"));
+    assert_eq!(result.outcomes, vec![HunkOutcome::Applied { fuzz: 1 }]);
+}
+
+#[test]
+fn should_write_rejected_hunks() {
+    let text = "--- a\n+++ b\n@@ -1 +1 @@\n-a\n+A\n@@ -3 +3 @@\n-c\n\\ No newline at end of file\n+C\n";
+    let patch = ::parse_patch(text).unwrap();
+    let old = ::split_lines("a\nb\nx");
+    let result = patch[0].apply_fuzzy(&old, &FuzzyOptions::default());
+    assert!(!result.is_complete());
+    assert_eq!(result.lines, ::split_lines("A\nb\nx"));
+    assert_eq!(
+        rejects(&patch[0], &result.outcomes),
+        "--- a\n+++ b\n@@ -3 +3 @@\n-c\n\\ No newline at end of file\n+C\n"
+    );
+}
@@ -0,0 +1,225 @@
+use std::cmp;
+
+use algorithm::{extend_shifted, push_common};
+use {common_affixes, diff_by, DiffResult};
+
+// Elements occurring more often than this in the old region are never used as
+// split points, same as git's `MAX_CHAIN_LENGTH`.
+const MAX_CHAIN_LENGTH: usize = 64;
+
+// A common run `old[old_start..old_start + len]` == `new[new_start..new_start + len]`
+// whose rarest element occurs `count` times in the old region.
+#[derive(Debug, PartialEq)]
+struct Region {
+    old_start: usize,
+    new_start: usize,
+    len: usize,
+    count: usize,
+}
+
+// Sentinel ending an occurrence chain.
+const NONE: usize = usize::MAX;
+
+// Occurrences of each token in the old region being split. The tables are sized
+// for the whole input once and reset after each split, so splitting a region
+// only touches the tokens of that region.
+struct Occurrences {
+    // Per token, the first position in the region and the number of positions.
+    first: Vec<usize>,
+    count: Vec<usize>,
+    // Per position, the next position holding the same token.
+    next: Vec<usize>,
+}
+
+impl Occurrences {
+    fn new(old: &[u32], new: &[u32]) -> Self {
+        let tokens = old.iter().chain(new).max().map_or(0, |&t| t as usize + 1);
+        Occurrences {
+            first: vec![NONE; tokens],
+            count: vec![0; tokens],
+            next: vec![NONE; old.len()],
+        }
+    }
+
+    fn fill(&mut self, old: &[u32]) {
+        for (i, &token) in old.iter().enumerate().rev() {
+            self.next[i] = self.first[token as usize];
+            self.first[token as usize] = i;
+            self.count[token as usize] += 1;
+        }
+    }
+
+    fn clear(&mut self, old: &[u32]) {
+        for &token in old {
+            self.first[token as usize] = NONE;
+            self.count[token as usize] = 0;
+        }
+    }
+
+    fn count(&self, token: u32) -> usize {
+        self.count.get(token as usize).map_or(0, |&c| c)
+    }
+}
+
+pub(crate) fn histogram(old: &[u32], new: &[u32]) -> Vec<DiffResult> {
+    let mut result = Vec::with_capacity(old.len() + new.len());
+    let mut occurrences = Occurrences::new(old, new);
+    histogram_range(old, new, 0, 0, &mut occurrences, &mut result);
+    result
+}
+
+// Recurses into the part left of each split and loops on the right one, like
+// git's xhistogram, so inputs splitting one element at a time cannot exhaust the
+// stack.
+fn histogram_range(
+    mut old: &[u32],
+    mut new: &[u32],
+    mut old_offset: usize,
+    mut new_offset: usize,
+    occurrences: &mut Occurrences,
+    result: &mut Vec<DiffResult>,
+) {
+    // Common suffixes of the regions entered by the loop, emitted innermost first.
+    let mut suffixes = Vec::new();
+    loop {
+        let (prefix_size, suffix_size) = common_affixes(old, new, &PartialEq::eq);
+        push_common(result, old_offset, new_offset, prefix_size);
+        suffixes.push((
+            old_offset + old.len() - suffix_size,
+            new_offset + new.len() - suffix_size,
+            suffix_size,
+        ));
+
+        let sliced_old = &old[prefix_size..(old.len() - suffix_size)];
+        let sliced_new = &new[prefix_size..(new.len() - suffix_size)];
+        let old_start = old_offset + prefix_size;
+        let new_start = new_offset + prefix_size;
+        let region = match split_region(sliced_old, sliced_new, occurrences) {
+            Some(region) => region,
+            None => {
+                let results = diff_by(sliced_old, sliced_new, PartialEq::eq);
+                extend_shifted(result, results, old_start, new_start);
+                break;
+            }
+        };
+        let old_end = region.old_start + region.len;
+        let new_end = region.new_start + region.len;
+        histogram_range(
+            &sliced_old[..region.old_start],
+            &sliced_new[..region.new_start],
+            old_start,
+            new_start,
+            occurrences,
+            result,
+        );
+        push_common(
+            result,
+            old_start + region.old_start,
+            new_start + region.new_start,
+            region.len,
+        );
+        old = &sliced_old[old_end..];
+        new = &sliced_new[new_end..];
+        old_offset = old_start + old_end;
+        new_offset = new_start + new_end;
+    }
+    for (old_index, new_index, len) in suffixes.into_iter().rev() {
+        push_common(result, old_index, new_index, len);
+    }
+}
+
+// Finds the common run to split on: the one whose rarest element is the least
+// frequent in `old`, preferring longer runs on ties. Returns `None` if every
+// shared element occurs more than `MAX_CHAIN_LENGTH` times.
+fn split_region(old: &[u32], new: &[u32], occurrences: &mut Occurrences) -> Option<Region> {
+    occurrences.fill(old);
+    let best = find_region(old, new, occurrences);
+    occurrences.clear(old);
+    best
+}
+
+fn find_region(old: &[u32], new: &[u32], occurrences: &Occurrences) -> Option<Region> {
+    let mut best: Option<Region> = None;
+    let mut j = 0;
+    while j < new.len() {
+        let mut next = j + 1;
+        let len = occurrences.count(new[j]);
+        if len == 0 || len > MAX_CHAIN_LENGTH {
+            j = next;
+            continue;
+        }
+        let mut i = occurrences.first[new[j] as usize];
+        while i != NONE {
+            let (mut old_start, mut new_start) = (i, j);
+            while old_start > 0 && new_start > 0 && old[old_start - 1] == new[new_start - 1] {
+                old_start -= 1;
+                new_start -= 1;
+            }
+            let (mut old_end, mut new_end) = (i + 1, j + 1);
+            while old_end < old.len() && new_end < new.len() && old[old_end] == new[new_end] {
+                old_end += 1;
+                new_end += 1;
+            }
+            let region = Region {
+                old_start,
+                new_start,
+                len: old_end - old_start,
+                count: old[old_start..old_end].iter().map(|&t| occurrences.count(t)).min().unwrap_or(0),
+            };
+            let better = match best {
+                Some(ref b) => region.count < b.count || (region.count == b.count && region.len > b.len),
+                None => true,
+            };
+            if better {
+                best = Some(region);
+            }
+            // Every start inside this run would only rediscover it.
+            next = cmp::max(next, new_end);
+            i = occurrences.next[i];
+        }
+        j = next;
+    }
+    best
+}
+
+#[test]
+fn should_split_on_rarest_region() {
+    let old = vec![1, 0, 2, 0, 3];
+    let new = vec![0, 3, 1, 0, 2];
+    let mut occurrences = Occurrences::new(&old, &new);
+    assert_eq!(
+        split_region(&old, &new, &mut occurrences),
+        Some(Region {
+            old_start: 0,
+            new_start: 2,
+            len: 3,
+            count: 1,
+        })
+    );
+}
+
+#[test]
+fn should_diff_with_histogram() {
+    let old = vec!["a", "}", "b", "}", "c"];
+    let new = vec!["a", "}", "c", "}"];
+    let result = ::diff_with_algorithm(&old, &new, ::Algorithm::Histogram);
+    let common: Vec<(usize, usize)> = result
+        .iter()
+        .filter_map(|r| match r {
+            DiffResult::Common(e) => Some((e.old_index.unwrap(), e.new_index.unwrap())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(common, vec![(0, 0), (1, 1), (4, 2)]);
+}
+
+#[test]
+fn should_not_overflow_stack_on_alternating_input() {
+    let n = 4_000;
+    let old: Vec<usize> = (0..n).collect();
+    let new: Vec<usize> = (0..n).map(|i| if i % 2 == 1 { n + i } else { i }).collect();
+    let result = ::std::thread::spawn(move || ::diff_with_algorithm(&old, &new, ::Algorithm::Histogram))
+        .join()
+        .expect("histogram diff does not overflow the stack");
+    assert_eq!(result.len(), n + n / 2);
+}
@@ -0,0 +1,275 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use unified::write_to_string;
+use DiffResult;
+
+/// Layout of the page written by `write_html`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HtmlView {
+    /// One column, removed lines above added ones.
+    #[default]
+    Unified,
+    /// Old lines on the left, new lines on the right.
+    Split,
+}
+
+/// Options used by `write_html`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlFormat<'a> {
+    /// Used as the page title and heading.
+    pub title: &'a str,
+    pub view: HtmlView,
+    /// Number of common lines kept visible around each change. Longer unchanged
+    /// regions are collapsed into a `<details>` element.
+    pub context: usize,
+}
+
+impl<'a> Default for HtmlFormat<'a> {
+    fn default() -> Self {
+        HtmlFormat {
+            title: "diff",
+            view: HtmlView::Unified,
+            context: 3,
+        }
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1em}\
+table{border-collapse:collapse;width:100%;table-layout:fixed;font-family:monospace}\
+td{padding:0 .4em;vertical-align:top;white-space:pre-wrap;word-break:break-all}\
+td.num{width:4em;color:#888;text-align:right;user-select:none}\
+td.sign{width:1em;user-select:none}\
+tr.removed td,td.removed{background:#ffebe9}\
+tr.added td,td.added{background:#e6ffec}\
+td.empty{background:#f6f8fa}\
+details summary{cursor:pointer;color:#555;background:#f1f8ff;padding:.2em .4em;font-family:monospace}";
+
+/// Escapes `text` for use in HTML text and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn content(line: &str) -> String {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    escape_html(line.strip_suffix('\r').unwrap_or(line))
+}
+
+fn number(index: Option<usize>) -> String {
+    index.map_or(String::new(), |i| (i + 1).to_string())
+}
+
+// Splits `results` into ranges, flagging the unchanged ones further than
+// `context` results away from any change as hidden.
+fn sections(results: &[DiffResult], context: usize) -> Vec<(Range<usize>, bool)> {
+    let mut sections: Vec<(Range<usize>, bool)> = Vec::new();
+    let mut push = |range: Range<usize>, hidden: bool| {
+        if range.is_empty() {
+            return;
+        }
+        if let Some(&mut (ref mut last, last_hidden)) = sections.last_mut() {
+            if last_hidden == hidden {
+                last.end = range.end;
+                return;
+            }
+        }
+        sections.push((range, hidden));
+    };
+    let mut i = 0;
+    while i < results.len() {
+        let start = i;
+        while i < results.len() && matches!(results[i], DiffResult::Common(_)) {
+            i += 1;
+        }
+        if start == i {
+            i += 1;
+            push(start..i, false);
+            continue;
+        }
+        let head = if start == 0 { 0 } else { context };
+        let tail = if i == results.len() { 0 } else { context };
+        if i - start > head + tail {
+            push(start..start + head, false);
+            push(start + head..i - tail, true);
+            push(i - tail..i, false);
+        } else {
+            push(start..i, false);
+        }
+    }
+    sections
+}
+
+fn write_unified_rows<W: Write>(out: &mut W, old: &[&str], new: &[&str], results: &[DiffResult]) -> io::Result<()> {
+    for result in results {
+        let (class, sign, e, line) = match *result {
+            DiffResult::Common(ref e) => ("common", " ", e, old[e.old_index.expect("common has old index")]),
+            DiffResult::Removed(ref e) => ("removed", "-", e, old[e.old_index.expect("removed has old index")]),
+            DiffResult::Added(ref e) => ("added", "+", e, new[e.new_index.expect("added has new index")]),
+        };
+        writeln!(
+            out,
+            "<tr class=\"{}\"><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+             <td class=\"sign\">{}</td><td class=\"code\">{}</td></tr>",
+            class,
+            number(e.old_index),
+            number(e.new_index),
+            sign,
+            content(line)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_split_row<W: Write>(out: &mut W, left: Option<(usize, &str, &str)>, right: Option<(usize, &str, &str)>) -> io::Result<()> {
+    out.write_all(b"<tr>")?;
+    for side in &[left, right] {
+        match *side {
+            Some((i, class, line)) => write!(
+                out,
+                "<td class=\"num\">{}</td><td class=\"code {}\">{}</td>",
+                i + 1,
+                class,
+                content(line)
+            )?,
+            None => out.write_all(b"<td class=\"num\"></td><td class=\"code empty\"></td>")?,
+        }
+    }
+    out.write_all(b"</tr>\n")
+}
+
+fn write_split_rows<W: Write>(out: &mut W, old: &[&str], new: &[&str], results: &[DiffResult]) -> io::Result<()> {
+    let mut removed: Vec<usize> = Vec::new();
+    let mut added: Vec<usize> = Vec::new();
+    for (n, result) in results.iter().enumerate() {
+        match *result {
+            DiffResult::Removed(ref e) => removed.extend(e.old_index),
+            DiffResult::Added(ref e) => added.extend(e.new_index),
+            DiffResult::Common(_) => (),
+        }
+        if matches!(results.get(n + 1), Some(&DiffResult::Common(_)) | None) {
+            for row in 0..removed.len().max(added.len()) {
+                let left = removed.get(row).map(|&i| (i, "removed", old[i]));
+                let right = added.get(row).map(|&i| (i, "added", new[i]));
+                write_split_row(out, left, right)?;
+            }
+            removed.clear();
+            added.clear();
+        }
+        if let DiffResult::Common(ref e) = *result {
+            let (o, n) = (e.old_index.expect("common has old index"), e.new_index.expect("common has new index"));
+            write_split_row(out, Some((o, "common", old[o])), Some((n, "common", new[n])))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, as a standalone
+/// HTML page with inline CSS and no external assets.
+///
+/// Line numbers are taken from the `DiffElement` indices, and all content is
+/// HTML escaped.
+pub fn write_html<W: Write>(
+    out: &mut W,
+    old: &[&str],
+    new: &[&str],
+    results: &[DiffResult],
+    format: &HtmlFormat,
+) -> io::Result<()> {
+    let title = escape_html(format.title);
+    let view = match format.view {
+        HtmlView::Unified => "unified",
+        HtmlView::Split => "split",
+    };
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(
+        out,
+        "<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head>",
+        title, STYLE
+    )?;
+    writeln!(out, "<body><h1>{}</h1>", title)?;
+    for (range, hidden) in sections(results, format.context) {
+        if hidden {
+            writeln!(
+                out,
+                "<details><summary>{} unchanged lines</summary>",
+                range.len()
+            )?;
+        }
+        writeln!(out, "<table class=\"diff {}\">", view)?;
+        match format.view {
+            HtmlView::Unified => write_unified_rows(out, old, new, &results[range])?,
+            HtmlView::Split => write_split_rows(out, old, new, &results[range])?,
+        }
+        writeln!(out, "</table>")?;
+        if hidden {
+            writeln!(out, "</details>")?;
+        }
+    }
+    writeln!(out, "</body></html>")
+}
+
+/// Same as `write_html`, but returns the page as a `String`.
+pub fn html_diff(old: &[&str], new: &[&str], results: &[DiffResult], format: &HtmlFormat) -> String {
+    write_to_string(|out| write_html(out, old, new, results, format))
+}
+
+#[test]
+fn should_escape_html() {
+    assert_eq!(escape_html("<a href=\"x\">&'</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;");
+}
+
+#[test]
+fn should_collapse_unchanged_regions() {
+    let old: Vec<u32> = (0..20).collect();
+    let mut new = old.clone();
+    new[10] = 100;
+    let results = ::diff(&old, &new);
+    assert_eq!(
+        sections(&results, 2),
+        vec![(0..8, true), (8..14, false), (14..21, true)]
+    );
+}
+
+#[test]
+fn should_render_unified_html() {
+    let old = vec!["<b>\n"];
+    let new = vec!["<i>\n"];
+    let page = html_diff(&old, &new, &::diff(&old, &new), &HtmlFormat::default());
+    assert!(page.starts_with("<!DOCTYPE html>\n"));
+    assert!(page.contains(
+        "<tr class=\"removed\"><td class=\"num\">1</td><td class=\"num\"></td>\
+         <td class=\"sign\">-</td><td class=\"code\">&lt;b&gt;</td></tr>"
+    ));
+    assert!(page.contains(
+        "<tr class=\"added\"><td class=\"num\"></td><td class=\"num\">1</td>\
+         <td class=\"sign\">+</td><td class=\"code\">&lt;i&gt;</td></tr>"
+    ));
+}
+
+#[test]
+fn should_render_split_html() {
+    let old = vec!["a\n", "b\n"];
+    let new = vec!["a\n", "c\n", "d\n"];
+    let format = HtmlFormat {
+        view: HtmlView::Split,
+        ..HtmlFormat::default()
+    };
+    let page = html_diff(&old, &new, &::diff(&old, &new), &format);
+    assert!(page.contains(
+        "<tr><td class=\"num\">2</td><td class=\"code removed\">b</td>\
+         <td class=\"num\">2</td><td class=\"code added\">c</td></tr>\n\
+         <tr><td class=\"num\"></td><td class=\"code empty\"></td>\
+         <td class=\"num\">3</td><td class=\"code added\">d</td></tr>"
+    ));
+}
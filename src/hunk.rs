@@ -0,0 +1,135 @@
+use std::cmp;
+
+use ops::group_ops;
+use {DiffOp, DiffResult};
+
+/// A group of changes together with their surrounding context, as returned by
+/// `hunks`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// Index of the first element of `old` covered by the hunk. For a hunk
+    /// covering no element of `old`, the index the insertion happens before.
+    pub old_start: usize,
+    pub old_len: usize,
+    /// Same as `old_start`, for `new`.
+    pub new_start: usize,
+    pub new_len: usize,
+    /// The operations covered by the hunk. Leading and trailing `Equal` ops are
+    /// trimmed to the context radius.
+    pub ops: Vec<DiffOp>,
+}
+
+impl Hunk {
+    fn from_ops(ops: Vec<DiffOp>) -> Self {
+        let first = &ops[0];
+        let last = &ops[ops.len() - 1];
+        let (old_start, new_start) = (first.old_range().start, first.new_range().start);
+        Hunk {
+            old_start,
+            old_len: last.old_range().end - old_start,
+            new_start,
+            new_len: last.new_range().end - new_start,
+            ops,
+        }
+    }
+}
+
+// Keeps `len` elements of an `Equal` op, from its start or from its end.
+fn take_equal(old_start: usize, new_start: usize, len: usize) -> DiffOp {
+    DiffOp::Equal {
+        old: old_start..old_start + len,
+        new: new_start..new_start + len,
+    }
+}
+
+/// Groups `results` into hunks with up to `context` common elements before and
+/// after each change. Changes whose context would overlap or touch share a hunk.
+pub fn hunks(results: &[DiffResult], context: usize) -> Vec<Hunk> {
+    let ops = group_ops(results);
+    let mut hunks = Vec::new();
+    let mut current: Vec<DiffOp> = Vec::new();
+    let last = ops.len().saturating_sub(1);
+    for (i, op) in ops.into_iter().enumerate() {
+        let (old, new) = match op {
+            DiffOp::Equal { ref old, ref new } => (old.clone(), new.clone()),
+            op => {
+                current.push(op);
+                continue;
+            }
+        };
+        let len = old.len();
+        let is_first = current.is_empty();
+        let is_last = i == last;
+        if is_first {
+            // Only keep the context leading into the next change.
+            let keep = cmp::min(len, context);
+            if !is_last && keep > 0 {
+                current.push(take_equal(old.end - keep, new.end - keep, keep));
+            }
+        } else if is_last || len > 2 * context {
+            let keep = cmp::min(len, context);
+            if keep > 0 {
+                current.push(take_equal(old.start, new.start, keep));
+            }
+            hunks.push(Hunk::from_ops(current));
+            current = Vec::new();
+            if !is_last && context > 0 {
+                current.push(take_equal(old.end - context, new.end - context, context));
+            }
+        } else {
+            current.push(DiffOp::Equal { old, new });
+        }
+    }
+    let has_change = current.iter().any(|op| !matches!(*op, DiffOp::Equal { .. }));
+    if has_change {
+        hunks.push(Hunk::from_ops(current));
+    }
+    hunks
+}
+
+#[test]
+fn should_group_changes_into_hunks() {
+    let old: Vec<u32> = (0..20).collect();
+    let mut new = old.clone();
+    new[2] = 100;
+    new[6] = 101;
+    new.remove(15);
+    let result = hunks(&::diff(&old, &new), 2);
+    assert_eq!(result.len(), 2);
+    assert_eq!(
+        (result[0].old_start, result[0].old_len, result[0].new_start, result[0].new_len),
+        (0, 9, 0, 9)
+    );
+    assert_eq!(
+        (result[1].old_start, result[1].old_len, result[1].new_start, result[1].new_len),
+        (13, 5, 13, 4)
+    );
+    assert_eq!(
+        result[1].ops,
+        vec![
+            DiffOp::Equal { old: 13..15, new: 13..15 },
+            DiffOp::Delete { old: 15..16, new_pos: 15 },
+            DiffOp::Equal { old: 16..18, new: 15..17 },
+        ]
+    );
+}
+
+#[test]
+fn should_return_no_hunks_without_changes() {
+    assert_eq!(hunks(&::diff(&[1, 2], &[1, 2]), 3), vec![]);
+}
+
+#[test]
+fn should_create_hunks_without_context() {
+    let result = hunks(&::diff(&[1, 2, 3], &[1, 4, 3]), 0);
+    assert_eq!(
+        result,
+        vec![Hunk {
+            old_start: 1,
+            old_len: 1,
+            new_start: 1,
+            new_len: 1,
+            ops: vec![DiffOp::Replace { old: 1..2, new: 1..2 }],
+        }]
+    );
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use {diff_by, DiffResult};
+
+// Maps equal elements to the same `u32` token, so later comparisons are integer
+// comparisons no matter how expensive `T::eq` is.
+pub(crate) struct Interner<'a, T: 'a> {
+    ids: HashMap<&'a T, u32>,
+}
+
+impl<'a, T: Hash + Eq + 'a> Interner<'a, T> {
+    pub(crate) fn new() -> Self {
+        Interner {
+            ids: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn intern(&mut self, items: &'a [T]) -> Vec<u32> {
+        items
+            .iter()
+            .map(|item| {
+                let next = self.ids.len();
+                *self.ids.entry(item).or_insert_with(|| {
+                    assert!(next < u32::MAX as usize, "too many distinct elements to intern");
+                    next as u32
+                })
+            })
+            .collect()
+    }
+}
+
+/// Same as `diff`, but first maps every element to an integer token so that the
+/// search only compares integers.
+///
+/// Worth it when comparing elements is expensive, e.g. for long `String` lines.
+/// The indices of the results still point into `old` and `new`.
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated.
+pub fn diff_interned<T: Hash + Eq>(old: &[T], new: &[T]) -> Vec<DiffResult> {
+    let mut interner = Interner::new();
+    let old_tokens = interner.intern(old);
+    let new_tokens = interner.intern(new);
+    diff_by(&old_tokens, &new_tokens, PartialEq::eq)
+}
+
+#[test]
+fn should_intern_equal_elements_to_the_same_token() {
+    let old = vec!["a".to_string(), "b".to_string(), "a".to_string()];
+    let new = vec!["b".to_string(), "c".to_string()];
+    let mut interner = Interner::new();
+    assert_eq!(interner.intern(&old), vec![0, 1, 0]);
+    assert_eq!(interner.intern(&new), vec![1, 2]);
+}
+
+#[test]
+fn should_diff_interned_strings() {
+    let old: Vec<String> = vec!["foo", "bar", "baz"].into_iter().map(String::from).collect();
+    let new: Vec<String> = vec!["foo", "baz", "hoge"].into_iter().map(String::from).collect();
+    assert_eq!(diff_interned(&old, &new), ::diff(&old, &new));
+}
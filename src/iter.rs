@@ -0,0 +1,141 @@
+use {common_affixes, reversed_body, DiffElement, DiffError, DiffLimits, DiffResult};
+
+/// Iterator over the same results as `diff`, in the same order. Created by
+/// `diff_iter`.
+pub struct DiffIter<'a, T: 'a> {
+    old: &'a [T],
+    new: &'a [T],
+    state: State,
+}
+
+enum State {
+    // Walking the common prefix; holds the next index to compare.
+    Prefix(usize),
+    // Popping the reversed script between prefix and suffix. The suffix starts at
+    // `old_suffix` in `old` and `new_suffix` in `new`.
+    Body {
+        reversed: Vec<DiffResult>,
+        old_suffix: usize,
+        new_suffix: usize,
+    },
+    Suffix {
+        old_index: usize,
+        new_index: usize,
+    },
+}
+
+/// Lazily computes the shortest edit script which turns `old` into `new`.
+///
+/// The common prefix is yielded while it is being compared, and the rest of the
+/// script is only computed once the prefix is exhausted, so callers which stop
+/// early pay only for what they consume.
+///
+/// # Panics
+///
+/// The iterator panics if the edit graph cannot be allocated.
+pub fn diff_iter<'a, T: PartialEq>(old: &'a [T], new: &'a [T]) -> DiffIter<'a, T> {
+    DiffIter {
+        old,
+        new,
+        state: State::Prefix(0),
+    }
+}
+
+impl<'a, T: PartialEq> DiffIter<'a, T> {
+    fn enter_body(&mut self, prefix_size: usize) {
+        let (_, suffix_size) = common_affixes(&self.old[prefix_size..], &self.new[prefix_size..], &PartialEq::eq);
+        let old_suffix = self.old.len() - suffix_size;
+        let new_suffix = self.new.len() - suffix_size;
+        let body = reversed_body(
+            &self.old[prefix_size..old_suffix],
+            &self.new[prefix_size..new_suffix],
+            prefix_size,
+            &PartialEq::eq,
+            &DiffLimits::default(),
+            |_| Ok::<bool, DiffError>(true),
+        );
+        let (reversed, _) = body.expect("failed to compute diff");
+        self.state = State::Body {
+            reversed,
+            old_suffix,
+            new_suffix,
+        };
+    }
+}
+
+impl<'a, T: PartialEq> Iterator for DiffIter<'a, T> {
+    type Item = DiffResult;
+
+    fn next(&mut self) -> Option<DiffResult> {
+        loop {
+            match self.state {
+                State::Prefix(i) => {
+                    if i < self.old.len() && i < self.new.len() && self.old[i] == self.new[i] {
+                        self.state = State::Prefix(i + 1);
+                        return Some(DiffResult::Common(DiffElement {
+                            old_index: Some(i),
+                            new_index: Some(i),
+                        }));
+                    }
+                    self.enter_body(i);
+                }
+                State::Body {
+                    ref mut reversed,
+                    old_suffix,
+                    new_suffix,
+                } => {
+                    if let Some(result) = reversed.pop() {
+                        return Some(result);
+                    }
+                    self.state = State::Suffix {
+                        old_index: old_suffix,
+                        new_index: new_suffix,
+                    };
+                }
+                State::Suffix {
+                    ref mut old_index,
+                    ref mut new_index,
+                } => {
+                    if *old_index == self.old.len() {
+                        return None;
+                    }
+                    let result = DiffResult::Common(DiffElement {
+                        old_index: Some(*old_index),
+                        new_index: Some(*new_index),
+                    });
+                    *old_index += 1;
+                    *new_index += 1;
+                    return Some(result);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn should_iterate_same_results_as_diff() {
+    let old = vec!["a", "b", "c", "d", "e"];
+    let new = vec!["a", "c", "x", "d", "e"];
+    let result: Vec<DiffResult> = diff_iter(&old, &new).collect();
+    assert_eq!(result, ::diff(&old, &new));
+    let empty: Vec<&str> = vec![];
+    assert_eq!(diff_iter(&empty, &empty).next(), None);
+}
+
+#[test]
+fn should_yield_prefix_before_computing_body() {
+    let old = vec!["a", "b", "c"];
+    let new = vec!["a", "x", "c"];
+    let mut iter = diff_iter(&old, &new);
+    assert_eq!(
+        iter.next(),
+        Some(DiffResult::Common(DiffElement {
+            old_index: Some(0),
+            new_index: Some(0),
+        }))
+    );
+    match iter.state {
+        State::Prefix(1) => (),
+        _ => panic!("body computed too early"),
+    }
+}
@@ -2,22 +2,122 @@
 #![cfg_attr(feature = "clippy", plugin(clippy))]
 #![allow(non_snake_case)]
 
+mod algorithm;
+mod color;
+mod context;
+mod fuzzy;
+mod histogram;
+mod html;
+mod hunk;
+mod intern;
+mod iter;
+mod merge;
+mod normal;
+mod ops;
+mod parse;
+mod patch;
+mod patience;
+mod side_by_side;
+mod unified;
+mod width;
+
+pub use algorithm::{diff_with_algorithm, Algorithm};
+pub use color::{colored_diff, write_colored, Color, ColorChoice, ColorFormat, Style, Theme};
+pub use context::{context_diff, write_context, ContextFormat};
+pub use fuzzy::{rejects, write_rejects, FuzzyApply, FuzzyOptions, HunkOutcome};
+pub use html::{escape_html, html_diff, write_html, HtmlFormat, HtmlView};
+pub use hunk::{hunks, Hunk};
+pub use intern::diff_interned;
+pub use iter::{diff_iter, DiffIter};
+pub use merge::{conflicts, merge3, merged_text, write_merge, MergeChunk, MergeFormat, MergeRegion};
+pub use normal::{ed_diff, normal_diff, rcs_diff, write_ed, write_normal, write_rcs};
+pub use ops::{group_ops, ops, DiffOp};
+pub use parse::{parse_patch, FilePatch, HunkLine, ParseError, TextHunk};
+pub use patch::{compose, invert, ApplyError, Patch, PatchOp};
+pub use side_by_side::{side_by_side, write_side_by_side, SideBySideFormat};
+pub use unified::{split_lines, unified_diff, write_unified, UnifiedFormat};
+
 use std::cmp;
+use std::convert::Infallible;
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::time::Instant;
 
 const NONE: u8 = 0;
 const REMOVED: u8 = 1;
-const COMMON: u8 = 2;
 const ADDED: u8 = 3;
 
+// `eq(x, y)` compares `A[x]` with `B[y]`, where `A` is the longer sequence of
+// length `M` and `B` the shorter one of length `N`.
 #[derive(Debug)]
-struct Ctx<'a, T: 'a> {
+struct Ctx<'a, F: 'a> {
     k: isize,
     base: isize,
-    A: &'a [T],
-    B: &'a [T],
-    ptr: &'a mut usize,
-    routes: &'a mut [usize],
-    diff_types: &'a mut [u8],
+    M: usize,
+    N: usize,
+    eq: &'a F,
+    track: Track<'a>,
+}
+
+// What the ids of the farthest points refer to.
+#[derive(Debug)]
+enum Track<'a> {
+    // Nothing, only the edit distance is needed. Ids are always 0.
+    Distance,
+    // Indices into a route table.
+    Routes(&'a mut Vec<Route>),
+    // The first point at which the path crosses the anti-diagonal
+    // `x + y == split`, encoded by `encode_split`. 0 until the path crosses it.
+    Split(isize),
+}
+
+impl<'a, F: 'a> Ctx<'a, F> {
+    // Records the edit step from the point `prev` followed by the snake from
+    // `start` to `end` on diagonal `k`, and returns the id of the new point.
+    fn record(&mut self, prev: usize, diff_type: u8, start: isize, end: isize) -> usize {
+        match self.track {
+            Track::Distance => 0,
+            Track::Routes(ref mut routes) => {
+                if diff_type == NONE && end == start {
+                    return prev;
+                }
+                routes.push(Route {
+                    prev: prev as u32,
+                    diff_type,
+                    len: (end - start) as usize,
+                });
+                routes.len() - 1
+            }
+            Track::Split(split) => {
+                if prev != 0 {
+                    return prev;
+                }
+                let (x, y) = (start + self.k, start);
+                if x + y >= split {
+                    encode_split(x, y, split)
+                } else if end + self.k + end >= split {
+                    // Each step of a snake moves two anti-diagonals further.
+                    let steps = (split - x - y + 1) / 2;
+                    encode_split(x + steps, y + steps, split)
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+// The first point past the anti-diagonal `split` lies on `split` or `split + 1`,
+// so its `x` and that offset are enough to recover it.
+fn encode_split(x: isize, y: isize, split: isize) -> usize {
+    (2 * x + (x + y - split)) as usize + 1
+}
+
+fn decode_split(id: usize, split: isize) -> (usize, usize) {
+    let x = (id - 1) / 2;
+    let y = split as usize + (id - 1) % 2 - x;
+    (x, y)
 }
 
 #[derive(Debug, PartialEq)]
@@ -39,36 +139,72 @@ struct FarthestPoint {
     id: usize,
 }
 
-fn back_trace<T: PartialEq + Clone>(
-    A: &[T],
-    B: &[T],
+// One recorded step of the edit graph walk: an edit of `diff_type` (`NONE` at
+// the origin) followed by a snake of `len` common elements. Ids are `u32`, which
+// `route_budget` guarantees to be enough.
+#[derive(Debug, Clone)]
+struct Route {
+    prev: u32,
+    diff_type: u8,
+    len: usize,
+}
+
+const ROOT: Route = Route {
+    prev: 0,
+    diff_type: NONE,
+    len: 0,
+};
+
+// Routes recorded per element of `A` and `B` before the search gives up on the
+// route table and splits the edit graph instead, keeping memory linear.
+const ROUTES_PER_ELEMENT: usize = 4;
+// Route table size below which inputs are never split.
+const MIN_ROUTE_BUDGET: usize = 1 << 16;
+
+fn route_budget(M: usize, N: usize) -> usize {
+    let budget = M.saturating_add(N).saturating_mul(ROUTES_PER_ELEMENT);
+    cmp::min(cmp::max(budget, MIN_ROUTE_BUDGET), u32::MAX as usize)
+}
+
+// Walks the routes back from `current`, which sits at `(x, y)` in the edit graph
+// of `A` and `B`, and appends the script leading to it to `result` in reverse
+// order. Indices into `old` and `new` are shifted by `offsets`.
+fn back_trace(
+    x: usize,
+    y: usize,
     current: &FarthestPoint,
     swapped: bool,
-    routes: &[usize],
-    diff_types: &[u8],
-    prefix_size: usize,
-) -> Vec<DiffResult> {
-    let M = A.len();
-    let N = B.len();
-    let mut result: Vec<DiffResult> = vec![];
-    let mut a = M - 1;
-    let mut b = N - 1;
-    let mut j = routes[current.id];
-    let mut diff_type = diff_types[current.id];
-    loop {
-        if j == 0 && diff_type == NONE {
-            break;
+    routes: &[Route],
+    offsets: (usize, usize),
+    result: &mut Vec<DiffResult>,
+) {
+    let (a_offset, b_offset) = if swapped {
+        (offsets.1, offsets.0)
+    } else {
+        offsets
+    };
+    let mut a = x.wrapping_sub(1);
+    let mut b = y.wrapping_sub(1);
+    let mut j = current.id;
+    while j != 0 {
+        let route = &routes[j];
+        for _ in 0..route.len {
+            let i = (Some(a.wrapping_add(a_offset)), Some(b.wrapping_add(b_offset)));
+            let (old_index, new_index) = if swapped { (i.1, i.0) } else { i };
+
+            result.push(DiffResult::Common(DiffElement { old_index, new_index }));
+            a = a.wrapping_sub(1);
+            b = b.wrapping_sub(1);
         }
-        let prev = j;
-        match diff_type {
+        match route.diff_type {
             ADDED => {
                 let old_index = if swapped {
                     None
                 } else {
-                    Some(a.wrapping_add(prefix_size))
+                    Some(a.wrapping_add(a_offset))
                 };
                 let new_index = if swapped {
-                    Some(a.wrapping_add(prefix_size))
+                    Some(a.wrapping_add(a_offset))
                 } else {
                     None
                 };
@@ -85,14 +221,14 @@ fn back_trace<T: PartialEq + Clone>(
             }
             REMOVED => {
                 let old_index = if swapped {
-                    Some(b.wrapping_add(prefix_size))
+                    Some(b.wrapping_add(b_offset))
                 } else {
                     None
                 };
                 let new_index = if swapped {
                     None
                 } else {
-                    Some(b.wrapping_add(prefix_size))
+                    Some(b.wrapping_add(b_offset))
                 };
                 let result_type = if swapped {
                     DiffResult::Removed
@@ -105,125 +241,374 @@ fn back_trace<T: PartialEq + Clone>(
                 }));
                 b = b.wrapping_sub(1);
             }
-            _ => {
-                let i = (Some(a.wrapping_add(prefix_size)), Some(b.wrapping_add(prefix_size)));
-                let (old_index, new_index) = if swapped {
-                    (i.1, i.0)
-                } else {
-                    i
-                };
-
-                result.push(DiffResult::Common(DiffElement { old_index, new_index }));
-                a = a.wrapping_sub(1);
-                b = b.wrapping_sub(1);
-            }
+            _ => (),
         };
-        j = routes[prev];
-        diff_type = diff_types[prev];
+        j = route.prev as usize;
     }
-    result.into_iter().rev().collect()
-}
-
-fn create_fp<T: PartialEq>(fp: &[FarthestPoint], ctx: &mut Ctx<T>) -> FarthestPoint {
-    if ctx.base < 1 as isize {
-        let base = (ctx.base + 1) as usize;
-        let prev = fp[base].id;
-        let y = fp[base].y + 1;
-        *ctx.ptr += 1;
-        ctx.routes[*ctx.ptr] = prev;
-        ctx.diff_types[*ctx.ptr] = REMOVED;
-        return FarthestPoint { y, id: *ctx.ptr };
+}
+
+// Picks the neighbour the point on diagonal `k` is reached from. Returns its id,
+// the `y` after the edit step and the kind of edit.
+fn create_fp<F>(fp: &[FarthestPoint], ctx: &Ctx<F>) -> (usize, isize, u8) {
+    if ctx.base < 1 {
+        let down = &fp[(ctx.base + 1) as usize];
+        return (down.id, down.y + 1, REMOVED);
     } else if ctx.base + 1 >= fp.len() as isize {
-        let base = (ctx.base - 1) as usize;
-        let prev = fp[base].id;
-        let y = fp[base].y;
-        *ctx.ptr += 1;
-        ctx.routes[*ctx.ptr] = prev;
-        ctx.diff_types[*ctx.ptr] = ADDED;
-        return FarthestPoint { y, id: *ctx.ptr };
+        let slide = &fp[(ctx.base - 1) as usize];
+        return (slide.id, slide.y, ADDED);
     }
 
     let slide = &fp[(ctx.base - 1) as usize];
     let down = &fp[(ctx.base + 1) as usize];
 
     if slide.y == -1 && down.y == -1 {
-        return FarthestPoint { y: 0, id: 0 };
+        return (0, 0, NONE);
     }
-    *ctx.ptr += 1;
-    if down.y == -1 || ctx.k == ctx.A.len() as isize || slide.y > down.y + 1 {
-        let prev = slide.id;
-        let y = slide.y;
-        ctx.routes[*ctx.ptr] = prev;
-        ctx.diff_types[*ctx.ptr] = ADDED;
-        return FarthestPoint { y, id: *ctx.ptr };
+    if down.y == -1 || ctx.k == ctx.M as isize || slide.y > down.y + 1 {
+        return (slide.id, slide.y, ADDED);
     }
-    let prev = down.id;
-    let y = down.y + 1;
-    ctx.routes[*ctx.ptr] = prev;
-    ctx.diff_types[*ctx.ptr] = REMOVED;
-    FarthestPoint { y, id: *ctx.ptr }
+    (down.id, down.y + 1, REMOVED)
 }
 
-fn snake<T: PartialEq>(fps: &[FarthestPoint], ctx: &mut Ctx<T>) -> FarthestPoint {
-    let M = ctx.A.len() as isize;
-    let N = ctx.B.len() as isize;
+fn snake<F: Fn(usize, usize) -> bool>(fps: &[FarthestPoint], ctx: &mut Ctx<F>) -> FarthestPoint {
+    let M = ctx.M as isize;
+    let N = ctx.N as isize;
     if ctx.k + N < 0 || M - ctx.k < 0 {
         return FarthestPoint { y: -1, id: 0 };
     }
-    let mut fp = create_fp(fps, ctx);
-    while fp.y + ctx.k < M && fp.y < N && ctx.A[(fp.y + ctx.k) as usize] == ctx.B[fp.y as usize] {
-        let prev = fp.id;
-        *ctx.ptr += 1;
-        fp.id = *ctx.ptr;
-        fp.y += 1;
-        ctx.routes[*ctx.ptr] = prev;
-        ctx.diff_types[*ctx.ptr] = COMMON;
+    let (prev, start, diff_type) = create_fp(fps, ctx);
+    let mut y = start;
+    while y + ctx.k < M && y < N && (ctx.eq)((y + ctx.k) as usize, y as usize) {
+        y += 1;
     }
-    fp
+    let id = ctx.record(prev, diff_type, start, y);
+    FarthestPoint { y, id }
 }
 
-pub fn diff<T: PartialEq + Clone>(old: &[T], new: &[T]) -> Vec<DiffResult> {
-    let new_len = new.len();
-    let old_len = old.len();
-    let common_prefix = old.iter().zip(new).take_while(|p| p.0 == p.1);
+/// Error returned by `try_diff` when the edit graph cannot be built.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffError {
+    /// Computing the size of the edit graph overflowed `usize`.
+    Overflow,
+    /// The allocator refused to reserve `requested` more elements.
+    AllocationFailed { requested: usize },
+    /// The input or the route table grew beyond a bound set in `DiffLimits`.
+    LimitExceeded { limit: usize, required: usize },
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiffError::Overflow => write!(f, "edit graph size overflows usize"),
+            DiffError::AllocationFailed { requested } => {
+                write!(f, "failed to allocate {} elements", requested)
+            }
+            DiffError::LimitExceeded { limit, required } => write!(
+                f,
+                "diff requires {} elements but the limit is {}",
+                required, limit
+            ),
+        }
+    }
+}
+
+impl error::Error for DiffError {}
+
+/// Upper bounds applied by `try_diff_with_limits`. `None` means unbounded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffLimits {
+    /// Maximum number of elements accepted in either `old` or `new`.
+    pub max_len: Option<usize>,
+    /// Maximum number of routes recorded while walking the edit graph.
+    pub max_routes: Option<usize>,
+}
+
+fn check_limit(limit: Option<usize>, required: usize) -> Result<(), DiffError> {
+    match limit {
+        Some(limit) if required > limit => Err(DiffError::LimitExceeded { limit, required }),
+        _ => Ok(()),
+    }
+}
+
+fn try_reserve<U>(v: &mut Vec<U>, additional: usize) -> Result<(), DiffError> {
+    v.try_reserve(additional)
+        .map_err(|_| DiffError::AllocationFailed {
+            requested: additional,
+        })
+}
+
+// Returns the lengths of the common prefix and the common suffix of `old` and
+// `new`. The suffix never overlaps the prefix.
+fn common_affixes<T, U, F>(old: &[T], new: &[U], eq: &F) -> (usize, usize)
+where
+    F: Fn(&T, &U) -> bool,
+{
+    let common_prefix = old.iter().zip(new).take_while(|p| eq(p.0, p.1));
     let prefix_size = common_prefix.count();
     let common_suffix = old
         .iter()
         .rev()
         .zip(new.iter().rev())
-        .take(cmp::min(old_len, new_len) - prefix_size)
-        .take_while(|p| p.0 == p.1);
-    let suffix_size = common_suffix.count();
-    let swapped = old_len < new_len;
+        .take(cmp::min(old.len(), new.len()) - prefix_size)
+        .take_while(|p| eq(p.0, p.1));
+    (prefix_size, common_suffix.count())
+}
+
+// Allocates the farthest point table for an `M` x `N` edit graph.
+fn farthest_points(M: usize, N: usize) -> Result<Vec<FarthestPoint>, DiffError> {
+    let size = M
+        .checked_add(N)
+        .and_then(|s| s.checked_add(1))
+        .filter(|s| *s <= isize::MAX as usize)
+        .ok_or(DiffError::Overflow)?;
+    let mut fp: Vec<FarthestPoint> = Vec::new();
+    try_reserve(&mut fp, size)?;
+    fp.resize(size, FarthestPoint { y: -1, id: 0 });
+    Ok(fp)
+}
+
+// Runs the O(NP) search until the farthest point on diagonal `D` reaches the end
+// of `B`, and returns the number of completed rounds. `before_round` is called
+// with the round's `P` and may abort the search by returning an error, or stop it
+// early by returning `false`.
+fn search<F, E, R>(ctx: &mut Ctx<F>, fp: &mut [FarthestPoint], mut before_round: R) -> Result<isize, E>
+where
+    F: Fn(usize, usize) -> bool,
+    R: FnMut(&mut Ctx<F>, isize) -> Result<bool, E>,
+{
+    let N = ctx.N as isize;
+    let offset = N;
+    let D = ctx.M as isize - N;
+    let mut P = 0;
+    while fp[(D + offset) as usize].y < N {
+        if !before_round(ctx, P)? {
+            break;
+        }
+        let mut k = -P;
+        while k < D {
+            let base = k + offset;
+            ctx.k = k;
+            ctx.base = base;
+            fp[base as usize] = snake(fp, ctx);
+            k += 1;
+        }
+        let mut k = D + P;
+        while k > D {
+            let base = k + offset;
+            ctx.k = k;
+            ctx.base = base;
+            fp[base as usize] = snake(fp, ctx);
+            k -= 1;
+        }
+        let base = D + offset;
+        ctx.k = D;
+        ctx.base = base;
+        fp[base as usize] = snake(fp, ctx);
+        P += 1;
+    }
+    Ok(P)
+}
+
+/// Computes the shortest edit script which turns `old` into `new`.
+///
+/// Routes are recorded per snake instead of per cell of the edit graph. When
+/// the route table would outgrow a size linear in `old.len() + new.len()`, the
+/// edit graph is cut in two at the middle of the shortest path and each half is
+/// solved on its own, so memory stays O(N+M) for the price of searching again.
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated. Use `try_diff` to handle that case.
+pub fn diff<T: PartialEq + Clone>(old: &[T], new: &[T]) -> Vec<DiffResult> {
+    try_diff(old, new).expect("failed to compute diff")
+}
+
+/// Same as `diff`, but reports size overflow and allocation failure instead of
+/// panicking or aborting.
+pub fn try_diff<T: PartialEq + Clone>(old: &[T], new: &[T]) -> Result<Vec<DiffResult>, DiffError> {
+    try_diff_with_limits(old, new, &DiffLimits::default())
+}
+
+/// Same as `try_diff`, but rejects inputs exceeding `limits`.
+pub fn try_diff_with_limits<T: PartialEq + Clone>(
+    old: &[T],
+    new: &[T],
+    limits: &DiffLimits,
+) -> Result<Vec<DiffResult>, DiffError> {
+    compute(old, new, &PartialEq::eq, limits, |_| Ok(true)).map(|(result, _)| result)
+}
+
+/// Same as `diff`, but compares elements with `eq` instead of `PartialEq`.
+///
+/// `old` and `new` may hold different element types.
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated.
+pub fn diff_by<T, U, F>(old: &[T], new: &[U], eq: F) -> Vec<DiffResult>
+where
+    F: Fn(&T, &U) -> bool,
+{
+    match compute(old, new, &eq, &DiffLimits::default(), |_| Ok::<bool, DiffError>(true)) {
+        Ok((result, _)) => result,
+        Err(e) => panic!("failed to compute diff: {}", e),
+    }
+}
+
+/// Same as `diff`, but compares the keys returned by `key` instead of the
+/// elements themselves.
+///
+/// `key` is called exactly once per element, and the indices of the results
+/// still point into `old` and `new`.
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated.
+pub fn diff_by_key<T, K, F>(old: &[T], new: &[T], mut key: F) -> Vec<DiffResult>
+where
+    K: PartialEq,
+    F: FnMut(&T) -> K,
+{
+    let old_keys: Vec<K> = old.iter().map(&mut key).collect();
+    let new_keys: Vec<K> = new.iter().map(&mut key).collect();
+    diff_by(&old_keys, &new_keys, PartialEq::eq)
+}
+
+/// Result of `diff_with_limit`.
+#[derive(Debug, PartialEq)]
+pub enum LimitedDiff {
+    /// The edit distance is within the limit; holds the same script as `diff`.
+    Complete(Vec<DiffResult>),
+    /// The edit distance is at least `lower_bound`, which exceeds the limit.
+    TooDifferent { lower_bound: usize },
+}
+
+enum LimitStop {
+    Failed(DiffError),
+    TooDifferent(usize),
+}
+
+impl From<DiffError> for LimitStop {
+    fn from(e: DiffError) -> Self {
+        LimitStop::Failed(e)
+    }
+}
+
+/// Same as `diff`, but gives up as soon as the edit distance provably exceeds
+/// `max_d`, so the worst case costs O((N+M) * max_d).
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated.
+pub fn diff_with_limit<T: PartialEq + Clone>(old: &[T], new: &[T], max_d: usize) -> LimitedDiff {
+    let result = compute(old, new, &PartialEq::eq, &DiffLimits::default(), |lower_bound| {
+        if lower_bound > max_d {
+            return Err(LimitStop::TooDifferent(lower_bound));
+        }
+        Ok(true)
+    });
+    match result {
+        Ok((result, _)) => LimitedDiff::Complete(result),
+        Err(LimitStop::TooDifferent(lower_bound)) => LimitedDiff::TooDifferent { lower_bound },
+        Err(LimitStop::Failed(e)) => panic!("failed to compute diff: {}", e),
+    }
+}
+
+// Shared implementation of the `diff` family. `on_round` is called with a lower
+// bound of the edit distance before each round of the search and may abort it
+// with an error. If it returns `false` instead, the search stops and the script
+// is finished from the farthest reaching point, or the body is replaced as a
+// whole once routes are no longer recorded; the returned flag is then `true` to
+// mark the script as approximate.
+fn compute<T, U, F, E, R>(
+    old: &[T],
+    new: &[U],
+    eq: &F,
+    limits: &DiffLimits,
+    on_round: R,
+) -> Result<(Vec<DiffResult>, bool), E>
+where
+    F: Fn(&T, &U) -> bool,
+    E: From<DiffError>,
+    R: FnMut(usize) -> Result<bool, E>,
+{
+    check_limit(limits.max_len, cmp::max(old.len(), new.len()))?;
+    let new_len = new.len();
+    let old_len = old.len();
+    let (prefix_size, suffix_size) = common_affixes(old, new, eq);
     let sliced_old = &old[prefix_size..(old_len - suffix_size)];
     let sliced_new = &new[prefix_size..(new_len - suffix_size)];
+    let (body, approximate) = reversed_body(sliced_old, sliced_new, prefix_size, eq, limits, on_round)?;
+
+    let size = body
+        .len()
+        .checked_add(prefix_size + suffix_size)
+        .ok_or(DiffError::Overflow)?;
+    let mut result: Vec<DiffResult> = Vec::new();
+    try_reserve(&mut result, size)?;
+    let mut p = 0;
+    while p < prefix_size {
+        result.push(DiffResult::Common(DiffElement {
+            old_index: Some(p),
+            new_index: Some(p),
+        }));
+        p += 1;
+    }
+    result.extend(body.into_iter().rev());
+    let mut s = 0;
+    let old_offset = sliced_old.len() + prefix_size;
+    let new_offset = sliced_new.len() + prefix_size;
+    while s < suffix_size {
+        let old_index = s + old_offset;
+        result.push(DiffResult::Common(DiffElement {
+            old_index: Some(old_index),
+            new_index: Some(new_offset + s),
+        }));
+        s += 1;
+    }
+    Ok((result, approximate))
+}
 
-    let (A, B) = if swapped {
-        (sliced_new, sliced_old)
+// Computes the script between `sliced_old` and `sliced_new`, which start at
+// `prefix_size` in the original slices and share neither prefix nor suffix.
+// The script is returned in reverse order, as `back_trace` produces it.
+fn reversed_body<T, U, F, E, R>(
+    sliced_old: &[T],
+    sliced_new: &[U],
+    prefix_size: usize,
+    eq: &F,
+    limits: &DiffLimits,
+    mut on_round: R,
+) -> Result<(Vec<DiffResult>, bool), E>
+where
+    F: Fn(&T, &U) -> bool,
+    E: From<DiffError>,
+    R: FnMut(usize) -> Result<bool, E>,
+{
+    let swapped = sliced_old.len() < sliced_new.len();
+    let (M, N) = if swapped {
+        (sliced_new.len(), sliced_old.len())
     } else {
-        (sliced_old, sliced_new)
+        (sliced_old.len(), sliced_new.len())
+    };
+    let graph_eq = |x: usize, y: usize| {
+        if swapped {
+            eq(&sliced_old[y], &sliced_new[x])
+        } else {
+            eq(&sliced_old[x], &sliced_new[y])
+        }
     };
 
     let mut result: Vec<DiffResult> = Vec::new();
-    let M = A.len();
-    let N = B.len();
-
-    if M == 0 && N == 0 && prefix_size == 0 && suffix_size == 0 {
-        return result;
-    }
 
     if N == 0 {
-        let mut p = 0;
-        while p < prefix_size {
-            result.push(DiffResult::Common(DiffElement {
-                old_index: Some(p),
-                new_index: Some(p),
-            }));
-            p += 1;
-        }
-
-        let mut o = prefix_size;
-        while o < M + prefix_size {
+        // Removing or adding everything is already the minimal script, so there is
+        // nothing to cut short here.
+        on_round(M)?;
+        try_reserve(&mut result, M)?;
+        let mut o = M + prefix_size;
+        while o > prefix_size {
+            o -= 1;
             if swapped {
                 result.push(DiffResult::Added(DiffElement {
                     old_index: None,
@@ -235,103 +620,430 @@ pub fn diff<T: PartialEq + Clone>(old: &[T], new: &[T]) -> Vec<DiffResult> {
                     new_index: None,
                 }));
             }
-            o += 1;
-        }
-
-        let mut s = 0;
-        let old_offset = sliced_old.len() + prefix_size;
-        let new_offset = sliced_new.len() + prefix_size;
-        while s < suffix_size {
-            let old_index = s + old_offset;
-            result.push(DiffResult::Common(DiffElement {
-                old_index: Some(old_index),
-                new_index: Some(s + new_offset),
-            }));
-            s += 1;
         }
-        return result;
+        return Ok((result, false));
     }
 
     let offset = N as isize;
     let D = (M - N) as isize;
-    let size = M + N + 1;
-    let mut fp: Vec<FarthestPoint> = vec![FarthestPoint { y: -1, id: 0 }; size];
-    let mut P = 0;
-    let mut ptr = 0;
-    let mut routes: Vec<usize> = vec![0; M * N + size + 1];
-    let mut diff_types: Vec<u8> = vec![0; M * N + size + 1];
+    let base = (D + offset) as usize;
+    let mut fp = farthest_points(M, N)?;
+    let mut routes: Vec<Route> = Vec::new();
+    try_reserve(&mut routes, 1)?;
+    routes.push(ROOT);
+
+    let outcome = {
+        let mut ctx = Ctx {
+            k: 0,
+            base: 0,
+            M,
+            N,
+            eq: &graph_eq,
+            track: Track::Routes(&mut routes),
+        };
+        search_routes(&mut ctx, &mut fp, limits.max_routes, &mut on_round)?
+    };
+    let from = match outcome {
+        RouteSearch::Finished(P) => {
+            let approximate = fp[base].y < N as isize;
+            try_reserve(&mut result, script_len(M, N, approximate, P)?)?;
+            if approximate {
+                let (x, y, current) = farthest_reaching(&fp, M, N, D, P);
+                let (old_pos, new_pos) = if swapped { (y, x) } else { (x, y) };
+                for n in (new_pos..sliced_new.len()).rev() {
+                    result.push(DiffResult::Added(DiffElement {
+                        old_index: None,
+                        new_index: Some(n + prefix_size),
+                    }));
+                }
+                for o in (old_pos..sliced_old.len()).rev() {
+                    result.push(DiffResult::Removed(DiffElement {
+                        old_index: Some(o + prefix_size),
+                        new_index: None,
+                    }));
+                }
+                back_trace(x, y, &current, swapped, &routes, (prefix_size, prefix_size), &mut result);
+            } else {
+                back_trace(M, N, &fp[base], swapped, &routes, (prefix_size, prefix_size), &mut result);
+            }
+            return Ok((result, approximate));
+        }
+        RouteSearch::OverBudget(P) => P,
+    };
+    drop(routes);
+
+    // The rounds before `from` already went through `on_round`.
+    let (P, split) = search_split(M, N, &graph_eq, &mut fp, |P| {
+        if P < from {
+            Ok(true)
+        } else {
+            on_round((D + 2 * P) as usize)
+        }
+    })?;
+    drop(fp);
+    try_reserve(&mut result, script_len(M, N, split.is_none(), P)?)?;
+    match split {
+        Some((x, y)) => {
+            let (old_mid, new_mid) = if swapped { (y, x) } else { (x, y) };
+            reversed_split(
+                &sliced_old[old_mid..],
+                &sliced_new[new_mid..],
+                prefix_size + old_mid,
+                prefix_size + new_mid,
+                eq,
+                &mut result,
+            )?;
+            reversed_split(
+                &sliced_old[..old_mid],
+                &sliced_new[..new_mid],
+                prefix_size,
+                prefix_size,
+                eq,
+                &mut result,
+            )?;
+            Ok((result, false))
+        }
+        None => {
+            // Without routes there is no path to finish from, so the whole body
+            // is replaced.
+            push_reversed_changes(
+                &mut result,
+                prefix_size..prefix_size + sliced_old.len(),
+                prefix_size..prefix_size + sliced_new.len(),
+            );
+            Ok((result, true))
+        }
+    }
+}
 
+// Returns the length of the script for an `M` x `N` edit graph searched in `P`
+// rounds. An approximate script may replace everything, so `M + N` is used as
+// its upper bound.
+fn script_len(M: usize, N: usize, approximate: bool, P: isize) -> Result<usize, DiffError> {
+    if approximate {
+        return M.checked_add(N).ok_or(DiffError::Overflow);
+    }
+    // `P - 1` elements of `B` are added, all others are common with `A`.
+    Ok(M + (P as usize - 1))
+}
+
+// Outcome of `search_routes`.
+enum RouteSearch {
+    // The search ran this many rounds, reaching the end unless `on_round`
+    // stopped it.
+    Finished(isize),
+    // Recording the round `P` would have outgrown `route_budget`.
+    OverBudget(isize),
+}
+
+// Runs `search` while recording routes in the table of `ctx`.
+fn search_routes<F, E, R>(
+    ctx: &mut Ctx<F>,
+    fp: &mut [FarthestPoint],
+    max_routes: Option<usize>,
+    mut on_round: R,
+) -> Result<RouteSearch, E>
+where
+    F: Fn(usize, usize) -> bool,
+    E: From<DiffError>,
+    R: FnMut(usize) -> Result<bool, E>,
+{
+    let D = (ctx.M - ctx.N) as isize;
+    let budget = route_budget(ctx.M, ctx.N);
+    let mut over_budget = None;
+    let P = search(ctx, fp, |ctx, P| {
+        let routes = match ctx.track {
+            Track::Routes(ref mut routes) => routes,
+            _ => unreachable!("routes are recorded"),
+        };
+        // Every diagonal visited in this round records at most one route, so
+        // reserving up front keeps `record` from reallocating.
+        let additional = (D + 2 * P + 1) as usize;
+        let required = routes.len().checked_add(additional).ok_or(DiffError::Overflow)?;
+        check_limit(max_routes, required)?;
+        if required > budget {
+            over_budget = Some(P);
+            return Ok(false);
+        }
+        try_reserve(routes, additional)?;
+        on_round((D + 2 * P) as usize)
+    })?;
+    Ok(match over_budget {
+        Some(P) => RouteSearch::OverBudget(P),
+        None => RouteSearch::Finished(P),
+    })
+}
+
+// Reruns the search from scratch without routes, and returns the number of
+// rounds along with the point at which the shortest path first gets past the
+// middle anti-diagonal of the edit graph, or `None` if `before_round` stopped
+// the search.
+fn search_split<F, E, R>(
+    M: usize,
+    N: usize,
+    eq: &F,
+    fp: &mut [FarthestPoint],
+    mut before_round: R,
+) -> Result<(isize, Option<(usize, usize)>), E>
+where
+    F: Fn(usize, usize) -> bool,
+    R: FnMut(isize) -> Result<bool, E>,
+{
+    for point in fp.iter_mut() {
+        *point = FarthestPoint { y: -1, id: 0 };
+    }
+    let split = ((M + N) / 2) as isize;
     let mut ctx = Ctx {
         k: 0,
         base: 0,
-        A,
-        B,
-        ptr: &mut ptr,
-        routes: &mut routes,
-        diff_types: &mut diff_types,
+        M,
+        N,
+        eq,
+        track: Track::Split(split),
     };
+    let P = search(&mut ctx, fp, |_, P| before_round(P))?;
+    // The end of the graph lies on diagonal `M - N`, at index `M`.
+    let end = &fp[M];
+    if end.y < N as isize {
+        return Ok((P, None));
+    }
+    Ok((P, Some(decode_split(end.id, split))))
+}
 
-    while fp[(D + offset) as usize].y < N as isize {
-        let mut k = -(P as isize);
-        while k < D as isize {
-            let base = k + offset as isize;
-            ctx.k = k;
-            ctx.base = base;
-            fp[base as usize] = snake(&fp, &mut ctx);
-            k += 1;
+// Appends the shortest script between `old` and `new`, whose indices start at
+// `old_offset` and `new_offset`, to `result` in reverse order. Graphs whose
+// route table would outgrow `route_budget` are cut in two at the middle of the
+// shortest path and solved one half after the other, which keeps memory at
+// O(N+M) for the price of searching again.
+fn reversed_split<T, U, F>(
+    old: &[T],
+    new: &[U],
+    old_offset: usize,
+    new_offset: usize,
+    eq: &F,
+    result: &mut Vec<DiffResult>,
+) -> Result<(), DiffError>
+where
+    F: Fn(&T, &U) -> bool,
+{
+    let (prefix_size, suffix_size) = common_affixes(old, new, eq);
+    let sliced_old = &old[prefix_size..(old.len() - suffix_size)];
+    let sliced_new = &new[prefix_size..(new.len() - suffix_size)];
+    let old_start = old_offset + prefix_size;
+    let new_start = new_offset + prefix_size;
+    push_reversed_common(
+        result,
+        old_start + sliced_old.len(),
+        new_start + sliced_new.len(),
+        suffix_size,
+    );
+
+    let swapped = sliced_old.len() < sliced_new.len();
+    let (M, N) = if swapped {
+        (sliced_new.len(), sliced_old.len())
+    } else {
+        (sliced_old.len(), sliced_new.len())
+    };
+    let graph_eq = |x: usize, y: usize| {
+        if swapped {
+            eq(&sliced_old[y], &sliced_new[x])
+        } else {
+            eq(&sliced_old[x], &sliced_new[y])
         }
-        let mut k = (D + P) as isize;
-        while k > D as isize {
-            let base = k + offset as isize;
-            ctx.k = k;
-            ctx.base = base;
-            fp[base as usize] = snake(&fp, &mut ctx);
-            k -= 1;
+    };
+
+    if N == 0 {
+        push_reversed_changes(
+            result,
+            old_start..old_start + sliced_old.len(),
+            new_start..new_start + sliced_new.len(),
+        );
+    } else {
+        let mut fp = farthest_points(M, N)?;
+        let mut routes: Vec<Route> = Vec::new();
+        try_reserve(&mut routes, 1)?;
+        routes.push(ROOT);
+        let outcome = {
+            let mut ctx = Ctx {
+                k: 0,
+                base: 0,
+                M,
+                N,
+                eq: &graph_eq,
+                track: Track::Routes(&mut routes),
+            };
+            search_routes(&mut ctx, &mut fp, None, |_| Ok::<bool, DiffError>(true))?
+        };
+        if let RouteSearch::Finished(_) = outcome {
+            let end = &fp[M];
+            back_trace(M, N, end, swapped, &routes, (old_start, new_start), result);
+        } else {
+            drop(routes);
+            let (_, split) = search_split(M, N, &graph_eq, &mut fp, |_| Ok::<bool, DiffError>(true))?;
+            drop(fp);
+            let (x, y) = split.expect("the search runs to the end");
+            let (old_mid, new_mid) = if swapped { (y, x) } else { (x, y) };
+            reversed_split(
+                &sliced_old[old_mid..],
+                &sliced_new[new_mid..],
+                old_start + old_mid,
+                new_start + new_mid,
+                eq,
+                result,
+            )?;
+            reversed_split(
+                &sliced_old[..old_mid],
+                &sliced_new[..new_mid],
+                old_start,
+                new_start,
+                eq,
+                result,
+            )?;
         }
-        let base = D + offset;
-        ctx.k = D;
-        ctx.base = base;
-        fp[base as usize] = snake(&fp, &mut ctx);
-        P += 1;
     }
+    push_reversed_common(result, old_offset, new_offset, prefix_size);
+    Ok(())
+}
 
-    let mut result: Vec<DiffResult> = vec![];
-    let mut p = 0;
-    while p < prefix_size {
+// Appends `len` common elements starting at `old_start` and `new_start` to
+// `result` in reverse order.
+fn push_reversed_common(result: &mut Vec<DiffResult>, old_start: usize, new_start: usize, len: usize) {
+    for i in (0..len).rev() {
         result.push(DiffResult::Common(DiffElement {
-            old_index: Some(p),
-            new_index: Some(p),
+            old_index: Some(old_start + i),
+            new_index: Some(new_start + i),
         }));
-        p += 1;
     }
-    let base = (D + offset) as usize;
-    let back_trace_result = back_trace(
-        A,
-        B,
-        &fp[base],
-        swapped,
-        &ctx.routes,
-        &ctx.diff_types,
-        prefix_size,
-    );
-    result.extend(back_trace_result);
-    let mut s = 0;
-    let old_offset = sliced_old.len() + prefix_size;
-    let new_offset = sliced_new.len() + prefix_size;
-    while s < suffix_size {
-        let old_index = s + old_offset;
-        result.push(DiffResult::Common(DiffElement {
-            old_index: Some(old_index),
-            new_index: Some(new_offset + s),
+}
+
+// Appends the removal of `old` followed by the addition of `new` to `result`
+// in reverse order.
+fn push_reversed_changes(result: &mut Vec<DiffResult>, old: Range<usize>, new: Range<usize>) {
+    for n in new.rev() {
+        result.push(DiffResult::Added(DiffElement {
+            old_index: None,
+            new_index: Some(n),
         }));
-        s += 1;
     }
-    result
+    for o in old.rev() {
+        result.push(DiffResult::Removed(DiffElement {
+            old_index: Some(o),
+            new_index: None,
+        }));
+    }
+}
+
+// Picks the point which got closest to the end of the edit graph during the
+// first `P` rounds, returning its `(x, y)` position along with it. Falls back to
+// the origin when no round has run yet.
+fn farthest_reaching(fp: &[FarthestPoint], M: usize, N: usize, D: isize, P: isize) -> (usize, usize, FarthestPoint) {
+    let offset = N as isize;
+    let mut best = (0, 0, FarthestPoint { y: 0, id: 0 });
+    let mut k = cmp::max(-(P - 1), -offset);
+    while k <= cmp::min(D + P - 1, M as isize) {
+        let point = &fp[(k + offset) as usize];
+        let x = point.y + k;
+        if point.y >= 0 && point.y <= N as isize && x >= 0 && x <= M as isize {
+            let (x, y) = (x as usize, point.y as usize);
+            if x + y > best.0 + best.1 {
+                best = (x, y, point.clone());
+            }
+        }
+        k += 1;
+    }
+    best
+}
+
+/// Result of `diff_with_deadline`.
+#[derive(Debug, PartialEq)]
+pub struct DeadlineDiff {
+    pub results: Vec<DiffResult>,
+    /// `true` when the deadline passed before the search finished. `results` is
+    /// then a valid script, but not necessarily the shortest one.
+    pub approximate: bool,
+}
+
+/// Same as `diff`, but stops searching once `deadline` has passed and finishes
+/// the script greedily from the point that got farthest so far.
+///
+/// The clock is checked between rounds of the search. If the inputs were too
+/// different to record routes for, nothing is known about the path yet and the
+/// differing part is replaced as a whole.
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated.
+pub fn diff_with_deadline<T: PartialEq + Clone>(old: &[T], new: &[T], deadline: Instant) -> DeadlineDiff {
+    let result = compute(old, new, &PartialEq::eq, &DiffLimits::default(), |_| {
+        Ok::<bool, DiffError>(Instant::now() < deadline)
+    });
+    match result {
+        Ok((results, approximate)) => DeadlineDiff {
+            results,
+            approximate,
+        },
+        Err(e) => panic!("failed to compute diff: {}", e),
+    }
+}
+
+/// Returns the length of the shortest edit script between `old` and `new`, i.e.
+/// the number of removed plus added elements.
+///
+/// This runs the same search as `diff` without recording routes, so it needs
+/// only O(N+M) memory.
+///
+/// # Panics
+///
+/// Panics if the farthest point table cannot be allocated.
+pub fn edit_distance<T: PartialEq>(old: &[T], new: &[T]) -> usize {
+    let (prefix_size, suffix_size) = common_affixes(old, new, &PartialEq::eq);
+    let sliced_old = &old[prefix_size..(old.len() - suffix_size)];
+    let sliced_new = &new[prefix_size..(new.len() - suffix_size)];
+    let swapped = sliced_old.len() < sliced_new.len();
+    let (M, N) = if swapped {
+        (sliced_new.len(), sliced_old.len())
+    } else {
+        (sliced_old.len(), sliced_new.len())
+    };
+    let eq = |x: usize, y: usize| {
+        if swapped {
+            sliced_old[y] == sliced_new[x]
+        } else {
+            sliced_old[x] == sliced_new[y]
+        }
+    };
+    if N == 0 {
+        return M;
+    }
+
+    let mut fp = farthest_points(M, N).expect("failed to allocate farthest points");
+    let mut ctx = Ctx {
+        k: 0,
+        base: 0,
+        M,
+        N,
+        eq: &eq,
+        track: Track::Distance,
+    };
+    let P = match search(&mut ctx, &mut fp, |_, _| Ok::<bool, Infallible>(true)) {
+        Ok(P) => P,
+        Err(e) => match e {},
+    };
+    M - N + 2 * (P as usize - 1)
+}
+
+/// Returns the length of the longest common subsequence of `old` and `new`.
+///
+/// # Panics
+///
+/// Panics if the farthest point table cannot be allocated.
+pub fn lcs_len<T: PartialEq>(old: &[T], new: &[T]) -> usize {
+    (old.len() + new.len() - edit_distance(old, new)) / 2
 }
 
 #[test]
 fn should_return_one_changed() {
-    let result = diff(&vec!["a"], &vec!["b"]);
+    let result = diff(&["a"], &["b"]);
     let expected = vec![
         DiffResult::Removed(DiffElement {
             old_index: Some(0),
@@ -357,7 +1069,7 @@ fn should_return_empty() {
 
 #[test]
 fn should_return_one_common() {
-    let result = diff(&vec!["a"], &vec!["a"]);
+    let result = diff(&["a"], &["a"]);
     let expected = vec![DiffResult::Common(DiffElement {
         old_index: Some(0),
         new_index: Some(0),
@@ -367,7 +1079,7 @@ fn should_return_one_common() {
 
 #[test]
 fn should_return_one_removed() {
-    let result = diff(&vec!["a"], &vec![]);
+    let result = diff(&["a"], &[]);
     let expected = vec![DiffResult::Removed(DiffElement {
         old_index: Some(0),
         new_index: None,
@@ -377,7 +1089,7 @@ fn should_return_one_removed() {
 
 #[test]
 fn should_return_one_added() {
-    let result = diff(&vec![], &vec!["a"]);
+    let result = diff(&[], &["a"]);
     let expected = vec![DiffResult::Added(DiffElement {
         old_index: None,
         new_index: Some(0),
@@ -387,7 +1099,7 @@ fn should_return_one_added() {
 
 #[test]
 fn should_return_two_changed() {
-    let result = diff(&vec!["a", "a"], &vec!["b", "b"]);
+    let result = diff(&["a", "a"], &["b", "b"]);
     let expected = vec![
         DiffResult::Removed(DiffElement {
             old_index: Some(0),
@@ -412,7 +1124,7 @@ fn should_return_two_changed() {
 
 #[test]
 fn should_create_diff_result_with_added() {
-    let result = diff(&vec!["abc", "c"], &vec!["abc", "bcd", "c"]);
+    let result = diff(&["abc", "c"], &["abc", "bcd", "c"]);
     let expected = vec![
         DiffResult::Common(DiffElement {
             old_index: Some(0),
@@ -433,13 +1145,7 @@ fn should_create_diff_result_with_added() {
 
 #[test]
 fn should_create_diff_result_with_added_common_no_suffix() {
-    let result = diff(&vec![
-        "common",
-    ], &vec![
-        "added",
-        "common",
-        "added",
-    ]);
+    let result = diff(&["common"], &["added", "common", "added"]);
     let expected = vec![
         DiffResult::Added(DiffElement {
             old_index: None,
@@ -460,13 +1166,7 @@ fn should_create_diff_result_with_added_common_no_suffix() {
 
 #[test]
 fn should_create_diff_result_with_added_common_no_suffix_swapped() {
-    let result = diff(&vec![
-        "added",
-        "common",
-        "added",
-    ], &vec![
-        "common",
-    ]);
+    let result = diff(&["added", "common", "added"], &["common"]);
     let expected = vec![
         DiffResult::Removed(DiffElement {
             old_index: Some(0),
@@ -487,7 +1187,7 @@ fn should_create_diff_result_with_added_common_no_suffix_swapped() {
 
 #[test]
 fn should_create_diff_result_with_added_swapped() {
-    let result = diff(&vec!["abc", "bcd", "c"], &vec!["abc", "c"]);
+    let result = diff(&["abc", "bcd", "c"], &["abc", "c"]);
     let expected = vec![
         DiffResult::Common(DiffElement {
             old_index: Some(0),
@@ -508,7 +1208,7 @@ fn should_create_diff_result_with_added_swapped() {
 
 #[test]
 fn should_create_diff_result_with_removed() {
-    let result = diff(&vec!["abc", "bcd", "c"], &vec!["abc", "c"]);
+    let result = diff(&["abc", "bcd", "c"], &["abc", "c"]);
     let expected = vec![
         DiffResult::Common(DiffElement {
             old_index: Some(0),
@@ -528,7 +1228,7 @@ fn should_create_diff_result_with_removed() {
 
 #[test]
 fn should_create_diff_result_without_new() {
-    let result = diff(&vec!["abc", "bcd", "c"], &vec![]);
+    let result = diff(&["abc", "bcd", "c"], &[]);
     let expected = vec![
         DiffResult::Removed(DiffElement {
             old_index: Some(0),
@@ -548,7 +1248,7 @@ fn should_create_diff_result_without_new() {
 
 #[test]
 fn should_create_diff_result_without_old() {
-    let result = diff(&vec![], &vec!["abc", "bcd", "c"]);
+    let result = diff(&[], &["abc", "bcd", "c"]);
     let expected = vec![
         DiffResult::Added(DiffElement {
             old_index: None,
@@ -568,7 +1268,7 @@ fn should_create_diff_result_without_old() {
 
 #[test]
 fn should_create_empty_result_with_empty_input() {
-    let result = diff(&vec![0u8; 0], &vec![0u8; 0]);
+    let result = diff(&[0u8; 0], &[0u8; 0]);
     let expected = vec![];
     assert_eq!(result, expected);
 }
@@ -576,8 +1276,8 @@ fn should_create_empty_result_with_empty_input() {
 #[test]
 fn should_create_one_removed_diff_result() {
     let result = diff(
-        &vec!["abc", "bcd", "c", "aaa", "bbb", "ccc"],
-        &vec!["abc", "c", "aaa", "bbb", "ccc"],
+        &["abc", "bcd", "c", "aaa", "bbb", "ccc"],
+        &["abc", "c", "aaa", "bbb", "ccc"],
     );
     let expected = vec![
         DiffResult::Common(DiffElement {
@@ -611,8 +1311,8 @@ fn should_create_one_removed_diff_result() {
 #[test]
 fn should_create_string_and_strength_diff_result() {
     let result = diff(
-        &vec!["s", "t", "r", "e", "n", "g", "t", "h"],
-        &vec!["s", "t", "r", "i", "n", "g"],
+        &["s", "t", "r", "e", "n", "g", "t", "h"],
+        &["s", "t", "r", "i", "n", "g"],
     );
     let expected = vec![
         DiffResult::Common(DiffElement {
@@ -654,3 +1354,257 @@ fn should_create_string_and_strength_diff_result() {
     ];
     assert_eq!(result, expected);
 }
+
+#[test]
+fn should_diff_large_inputs_with_few_changes() {
+    let old: Vec<usize> = (0..100_000).collect();
+    let mut new = old.clone();
+    new[10] = 0;
+    new.remove(50_000);
+    new.insert(90_000, 1);
+    let result = diff(&old, &new);
+    let removed: Vec<usize> = result
+        .iter()
+        .filter_map(|r| match r {
+            DiffResult::Removed(e) => e.old_index,
+            _ => None,
+        })
+        .collect();
+    let added: Vec<usize> = result
+        .iter()
+        .filter_map(|r| match r {
+            DiffResult::Added(e) => e.new_index,
+            _ => None,
+        })
+        .collect();
+    assert_eq!(removed, vec![10, 50_000]);
+    assert_eq!(added, vec![10, 90_000]);
+}
+
+// Checks that `result` turns `old` into `new` and returns its number of common
+// elements.
+#[cfg(test)]
+fn check_script<T: PartialEq>(old: &[T], new: &[T], result: &[DiffResult]) -> usize {
+    let (mut o, mut n, mut common) = (0, 0, 0);
+    for r in result {
+        match r {
+            DiffResult::Common(e) => {
+                assert_eq!((e.old_index, e.new_index), (Some(o), Some(n)));
+                assert!(old[o] == new[n]);
+                o += 1;
+                n += 1;
+                common += 1;
+            }
+            DiffResult::Removed(e) => {
+                assert_eq!((e.old_index, e.new_index), (Some(o), None));
+                o += 1;
+            }
+            DiffResult::Added(e) => {
+                assert_eq!((e.old_index, e.new_index), (None, Some(n)));
+                n += 1;
+            }
+        }
+    }
+    assert_eq!((o, n), (old.len(), new.len()));
+    common
+}
+
+#[test]
+fn should_diff_large_fully_different_inputs() {
+    for &(old_len, new_len) in &[(2_000, 2_000), (20_000, 100), (100, 20_000)] {
+        let old: Vec<usize> = (0..old_len).collect();
+        let new: Vec<usize> = (old_len..old_len + new_len).collect();
+        let result = diff(&old, &new);
+        assert_eq!(check_script(&old, &new, &result), 0);
+        assert_eq!(result.len(), old_len + new_len);
+    }
+}
+
+#[test]
+fn should_keep_split_diffs_minimal() {
+    let old: Vec<usize> = (0..3_000).map(|i| i % 7).collect();
+    let new: Vec<usize> = (0..2_500).map(|i| i % 5).collect();
+    let result = diff(&old, &new);
+    assert_eq!(check_script(&old, &new, &result), lcs_len(&old, &new));
+}
+
+#[test]
+fn should_return_ok_from_try_diff() {
+    let result = try_diff(&["a", "b"], &["a", "c"]).unwrap();
+    assert_eq!(result, diff(&["a", "b"], &["a", "c"]));
+}
+
+#[test]
+fn should_reject_inputs_over_max_len() {
+    let limits = DiffLimits {
+        max_len: Some(2),
+        ..DiffLimits::default()
+    };
+    let result = try_diff_with_limits(&["a", "b", "c"], &["a"], &limits);
+    assert_eq!(
+        result,
+        Err(DiffError::LimitExceeded {
+            limit: 2,
+            required: 3,
+        })
+    );
+}
+
+#[test]
+fn should_reject_diffs_over_max_routes() {
+    let limits = DiffLimits {
+        max_routes: Some(8),
+        ..DiffLimits::default()
+    };
+    let old: Vec<u8> = vec![0; 10];
+    let new: Vec<u8> = vec![1; 10];
+    match try_diff_with_limits(&old, &new, &limits) {
+        Err(DiffError::LimitExceeded { limit: 8, .. }) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn should_fail_to_allocate_removing_everything() {
+    let old = vec![(); 1 << 60];
+    match try_diff(&old, &[]) {
+        Err(DiffError::AllocationFailed { requested }) => assert_eq!(requested, 1 << 60),
+        other => panic!("unexpected result {:?}", other.map(|r| r.len())),
+    }
+}
+
+#[test]
+fn should_compute_edit_distance() {
+    assert_eq!(edit_distance::<u8>(&[], &[]), 0);
+    assert_eq!(edit_distance(&["a"], &["a"]), 0);
+    assert_eq!(edit_distance(&["a"], &["b"]), 2);
+    assert_eq!(edit_distance(&["a", "b", "c"], &[]), 3);
+    assert_eq!(
+        edit_distance(
+            &["s", "t", "r", "e", "n", "g", "t", "h"],
+            &["s", "t", "r", "i", "n", "g"],
+        ),
+        4
+    );
+}
+
+#[test]
+fn should_compute_lcs_len() {
+    assert_eq!(lcs_len(&["a", "b", "c"], &["b", "c", "d"]), 2);
+    assert_eq!(
+        lcs_len(
+            &["s", "t", "r", "e", "n", "g", "t", "h"],
+            &["s", "t", "r", "i", "n", "g"],
+        ),
+        5
+    );
+}
+
+#[test]
+fn should_complete_diff_within_limit() {
+    let result = diff_with_limit(&["a", "b", "c"], &["a", "c"], 1);
+    assert_eq!(
+        result,
+        LimitedDiff::Complete(diff(&["a", "b", "c"], &["a", "c"]))
+    );
+}
+
+#[test]
+fn should_give_up_when_too_different() {
+    let result = diff_with_limit(&["a", "b", "c"], &["d", "e", "f"], 4);
+    assert_eq!(result, LimitedDiff::TooDifferent { lower_bound: 6 });
+    let result = diff_with_limit(&["a", "b", "c"], &[], 2);
+    assert_eq!(result, LimitedDiff::TooDifferent { lower_bound: 3 });
+}
+
+#[test]
+fn should_finish_before_deadline() {
+    let deadline = Instant::now() + std::time::Duration::from_secs(60);
+    let result = diff_with_deadline(&["a", "b", "c"], &["a", "c", "d"], deadline);
+    assert_eq!(
+        result,
+        DeadlineDiff {
+            results: diff(&["a", "b", "c"], &["a", "c", "d"]),
+            approximate: false,
+        }
+    );
+}
+
+#[test]
+fn should_return_approximate_diff_after_deadline() {
+    let result = diff_with_deadline(&["a", "b", "c"], &["a", "c", "d"], Instant::now());
+    let expected = vec![
+        DiffResult::Common(DiffElement {
+            old_index: Some(0),
+            new_index: Some(0),
+        }),
+        DiffResult::Removed(DiffElement {
+            old_index: Some(1),
+            new_index: None,
+        }),
+        DiffResult::Removed(DiffElement {
+            old_index: Some(2),
+            new_index: None,
+        }),
+        DiffResult::Added(DiffElement {
+            old_index: None,
+            new_index: Some(1),
+        }),
+        DiffResult::Added(DiffElement {
+            old_index: None,
+            new_index: Some(2),
+        }),
+    ];
+    assert_eq!(
+        result,
+        DeadlineDiff {
+            results: expected,
+            approximate: true,
+        }
+    );
+}
+
+#[test]
+fn should_diff_by_custom_equality() {
+    let result = diff_by(&["Foo", "bar"], &["foo", "BAZ"], |a, b| {
+        a.eq_ignore_ascii_case(b)
+    });
+    let expected = vec![
+        DiffResult::Common(DiffElement {
+            old_index: Some(0),
+            new_index: Some(0),
+        }),
+        DiffResult::Removed(DiffElement {
+            old_index: Some(1),
+            new_index: None,
+        }),
+        DiffResult::Added(DiffElement {
+            old_index: None,
+            new_index: Some(1),
+        }),
+    ];
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn should_diff_by_across_element_types() {
+    let old = vec![1.0, 2.0, 3.0];
+    let new = vec!["1", "3"];
+    let result = diff_by(&old, &new, |a: &f64, b: &&str| {
+        (a - b.parse::<f64>().unwrap()).abs() < 1e-9
+    });
+    assert_eq!(result, diff(&[1, 2, 3], &[1, 3]));
+}
+
+#[test]
+fn should_diff_by_key() {
+    let old = vec![(1, "a"), (2, "b"), (3, "c")];
+    let new = vec![(1, "x"), (3, "y"), (4, "z")];
+    let mut calls = 0;
+    let result = diff_by_key(&old, &new, |r| {
+        calls += 1;
+        r.0
+    });
+    assert_eq!(calls, 6);
+    assert_eq!(result, diff(&[1, 2, 3], &[1, 3, 4]));
+}
@@ -0,0 +1,222 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use unified::write_to_string;
+use {diff, DiffResult};
+
+/// Matching ranges of the three inputs of `merge3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeRegion {
+    pub base: Range<usize>,
+    pub ours: Range<usize>,
+    pub theirs: Range<usize>,
+}
+
+/// A region of the result of `merge3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeChunk {
+    /// Left untouched by both sides.
+    Unchanged(MergeRegion),
+    /// Changed by `ours` only, or identically by both sides.
+    Ours(MergeRegion),
+    /// Changed by `theirs` only.
+    Theirs(MergeRegion),
+    /// Changed differently by both sides.
+    Conflict(MergeRegion),
+}
+
+// Maps each element of `base` to its index in the other sequence, if common.
+fn base_matches(len: usize, results: &[DiffResult]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; len];
+    for result in results {
+        if let DiffResult::Common(ref e) = *result {
+            matches[e.old_index.expect("common has old index")] = e.new_index;
+        }
+    }
+    matches
+}
+
+/// Merges the changes made by `ours` and `theirs` to their common ancestor
+/// `base`, like `diff3`.
+///
+/// Both sides are diffed against `base` and aligned on the elements they both
+/// kept. Changes that do not overlap are merged automatically, overlapping
+/// ones produce a `MergeChunk::Conflict` unless they are identical.
+pub fn merge3<T: PartialEq + Clone>(base: &[T], ours: &[T], theirs: &[T]) -> Vec<MergeChunk> {
+    let ours_of = base_matches(base.len(), &diff(base, ours));
+    let theirs_of = base_matches(base.len(), &diff(base, theirs));
+    let mut chunks = Vec::new();
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // Next element kept by both sides, or the end of all three.
+        let (m, jm, km) = (i..base.len())
+            .filter_map(|m| match (ours_of[m], theirs_of[m]) {
+                (Some(jm), Some(km)) => Some((m, jm, km)),
+                _ => None,
+            })
+            .next()
+            .unwrap_or((base.len(), ours.len(), theirs.len()));
+        if m > i || jm > j || km > k {
+            let region = MergeRegion {
+                base: i..m,
+                ours: j..jm,
+                theirs: k..km,
+            };
+            let (b, o, t) = (&base[i..m], &ours[j..jm], &theirs[k..km]);
+            chunks.push(if b == o {
+                MergeChunk::Theirs(region)
+            } else if b == t || o == t {
+                MergeChunk::Ours(region)
+            } else {
+                MergeChunk::Conflict(region)
+            });
+        }
+        if m == base.len() {
+            return chunks;
+        }
+        let mut end = m + 1;
+        while end < base.len() && ours_of[end] == Some(jm + end - m) && theirs_of[end] == Some(km + end - m) {
+            end += 1;
+        }
+        i = end;
+        j = jm + end - m;
+        k = km + end - m;
+        chunks.push(MergeChunk::Unchanged(MergeRegion {
+            base: m..i,
+            ours: jm..j,
+            theirs: km..k,
+        }));
+    }
+}
+
+/// Returns the conflicting regions among `chunks`.
+pub fn conflicts(chunks: &[MergeChunk]) -> Vec<&MergeRegion> {
+    chunks
+        .iter()
+        .filter_map(|chunk| match *chunk {
+            MergeChunk::Conflict(ref region) => Some(region),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Labels used by `write_merge` for the conflict markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeFormat<'a> {
+    /// Written after `<<<<<<<`.
+    pub ours_label: &'a str,
+    /// Written after `|||||||`.
+    pub base_label: &'a str,
+    /// Written after `>>>>>>>`.
+    pub theirs_label: &'a str,
+    /// Whether conflicts include the `base` lines, as in git's `diff3` style.
+    pub show_base: bool,
+}
+
+impl<'a> Default for MergeFormat<'a> {
+    fn default() -> Self {
+        MergeFormat {
+            ours_label: "ours",
+            base_label: "base",
+            theirs_label: "theirs",
+            show_base: true,
+        }
+    }
+}
+
+// Writes `lines`, terminating the last one when a marker follows.
+fn write_lines<W: Write>(out: &mut W, lines: &[&str], terminate: bool) -> io::Result<()> {
+    for line in lines {
+        out.write_all(line.as_bytes())?;
+    }
+    if terminate && lines.last().map_or(false, |l| !l.ends_with('\n')) {
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Writes the lines merged by `merge3`, with conflicts surrounded by git-style
+/// `<<<<<<<`, `|||||||`, `=======` and `>>>>>>>` markers.
+///
+/// Lines are expected to keep their terminators, see `split_lines`.
+pub fn write_merge<W: Write>(
+    out: &mut W,
+    base: &[&str],
+    ours: &[&str],
+    theirs: &[&str],
+    chunks: &[MergeChunk],
+    format: &MergeFormat,
+) -> io::Result<()> {
+    for chunk in chunks {
+        match *chunk {
+            MergeChunk::Unchanged(ref region) => write_lines(out, &base[region.base.clone()], false)?,
+            MergeChunk::Ours(ref region) => write_lines(out, &ours[region.ours.clone()], false)?,
+            MergeChunk::Theirs(ref region) => write_lines(out, &theirs[region.theirs.clone()], false)?,
+            MergeChunk::Conflict(ref conflict) => {
+                writeln!(out, "<<<<<<< {}", format.ours_label)?;
+                write_lines(out, &ours[conflict.ours.clone()], true)?;
+                if format.show_base {
+                    writeln!(out, "||||||| {}", format.base_label)?;
+                    write_lines(out, &base[conflict.base.clone()], true)?;
+                }
+                writeln!(out, "=======")?;
+                write_lines(out, &theirs[conflict.theirs.clone()], true)?;
+                writeln!(out, ">>>>>>> {}", format.theirs_label)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write_merge`, but returns the merged text as a `String`.
+pub fn merged_text(base: &[&str], ours: &[&str], theirs: &[&str], chunks: &[MergeChunk], format: &MergeFormat) -> String {
+    write_to_string(|out| write_merge(out, base, ours, theirs, chunks, format))
+}
+
+#[cfg(test)]
+fn region(base: Range<usize>, ours: Range<usize>, theirs: Range<usize>) -> MergeRegion {
+    MergeRegion { base, ours, theirs }
+}
+
+#[test]
+fn should_merge_disjoint_changes() {
+    let base = vec!["a", "b", "c", "d", "e"];
+    let ours = vec!["a", "B", "c", "d", "e"];
+    let theirs = vec!["a", "b", "c", "d", "E", "f"];
+    assert_eq!(
+        merge3(&base, &ours, &theirs),
+        vec![
+            MergeChunk::Unchanged(region(0..1, 0..1, 0..1)),
+            MergeChunk::Ours(region(1..2, 1..2, 1..2)),
+            MergeChunk::Unchanged(region(2..4, 2..4, 2..4)),
+            MergeChunk::Theirs(region(4..5, 4..5, 4..6)),
+        ]
+    );
+}
+
+#[test]
+fn should_merge_identical_changes() {
+    let base = vec![1, 2, 3];
+    let ours = vec![1, 4, 5, 3];
+    assert_eq!(
+        merge3(&base, &ours, &ours),
+        vec![
+            MergeChunk::Unchanged(region(0..1, 0..1, 0..1)),
+            MergeChunk::Ours(region(1..2, 1..3, 1..3)),
+            MergeChunk::Unchanged(region(2..3, 3..4, 3..4)),
+        ]
+    );
+}
+
+#[test]
+fn should_render_conflicts() {
+    let base = ::split_lines("a\nb\nc");
+    let ours = ::split_lines("a\nx\nc");
+    let theirs = ::split_lines("a\ny\nc");
+    let chunks = merge3(&base, &ours, &theirs);
+    assert_eq!(conflicts(&chunks), vec![&region(1..2, 1..2, 1..2)]);
+    assert_eq!(
+        merged_text(&base, &ours, &theirs, &chunks, &MergeFormat::default()),
+        "a\n<<<<<<< ours\nx\n||||||| base\nb\n=======\ny\n>>>>>>> theirs\nc"
+    );
+}
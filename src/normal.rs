@@ -0,0 +1,157 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use ops::group_ops;
+use unified::{write_line, write_to_string};
+use {DiffOp, DiffResult};
+
+// Formats a non-empty range of 0-based indices as 1-based `first,last`, or as a
+// single number for one line.
+fn line_range(range: &Range<usize>) -> String {
+    if range.len() == 1 {
+        format!("{}", range.start + 1)
+    } else {
+        format!("{},{}", range.start + 1, range.end)
+    }
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, in the POSIX
+/// "normal" format printed by `diff` without options, e.g. `3c3,4`.
+///
+/// Lines are expected to keep their terminators, see `split_lines`.
+pub fn write_normal<W: Write>(out: &mut W, old: &[&str], new: &[&str], results: &[DiffResult]) -> io::Result<()> {
+    for op in group_ops(results) {
+        match op {
+            DiffOp::Equal { .. } => continue,
+            DiffOp::Delete { old: ref range, new_pos } => {
+                writeln!(out, "{}d{}", line_range(range), new_pos)?;
+            }
+            DiffOp::Insert { old_pos, new: ref range } => {
+                writeln!(out, "{}a{}", old_pos, line_range(range))?;
+            }
+            DiffOp::Replace {
+                old: ref old_range,
+                new: ref new_range,
+            } => {
+                writeln!(out, "{}c{}", line_range(old_range), line_range(new_range))?;
+            }
+        }
+        for line in &old[op.old_range()] {
+            write_line(out, "< ", line)?;
+        }
+        if let DiffOp::Replace { .. } = op {
+            out.write_all(b"---\n")?;
+        }
+        for line in &new[op.new_range()] {
+            write_line(out, "> ", line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, as an `ed`
+/// script turning `old` into `new`, as GNU `diff -e` does.
+///
+/// Commands are written from the end of the file backwards so that earlier line
+/// numbers stay valid. A line consisting of a single `.` is escaped the way GNU
+/// diff does it. `ed` cannot express a missing newline at the end of the file,
+/// so every line is written terminated.
+pub fn write_ed<W: Write>(out: &mut W, new: &[&str], results: &[DiffResult]) -> io::Result<()> {
+    for op in group_ops(results).into_iter().rev() {
+        match op {
+            DiffOp::Equal { .. } => continue,
+            DiffOp::Delete { old: ref range, .. } => {
+                writeln!(out, "{}d", line_range(range))?;
+                continue;
+            }
+            DiffOp::Insert { old_pos, .. } => writeln!(out, "{}a", old_pos)?,
+            DiffOp::Replace { old: ref range, .. } => writeln!(out, "{}c", line_range(range))?,
+        }
+        let mut insert_mode = true;
+        for line in &new[op.new_range()] {
+            if !insert_mode {
+                out.write_all(b"a\n")?;
+                insert_mode = true;
+            }
+            let content = line.strip_suffix('\n').unwrap_or(line);
+            if content == "." {
+                // A lone `.` would end insert mode, so insert `..` and strip a dot.
+                out.write_all(b"..\n.\ns/.//\n")?;
+                insert_mode = false;
+            } else {
+                writeln!(out, "{}", content)?;
+            }
+        }
+        if insert_mode {
+            out.write_all(b".\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, in the RCS
+/// format printed by GNU `diff -n`, e.g. `d3 1` followed by `a3 2`.
+///
+/// Line numbers refer to `old`. Lines are written verbatim.
+pub fn write_rcs<W: Write>(out: &mut W, new: &[&str], results: &[DiffResult]) -> io::Result<()> {
+    for op in group_ops(results) {
+        let old_range = op.old_range();
+        let new_range = op.new_range();
+        if let DiffOp::Equal { .. } = op {
+            continue;
+        }
+        if !old_range.is_empty() {
+            writeln!(out, "d{} {}", old_range.start + 1, old_range.len())?;
+        }
+        if !new_range.is_empty() {
+            writeln!(out, "a{} {}", old_range.end, new_range.len())?;
+            for line in &new[new_range] {
+                out.write_all(line.as_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write_normal`, but returns the text as a `String`.
+pub fn normal_diff(old: &[&str], new: &[&str], results: &[DiffResult]) -> String {
+    write_to_string(|out| write_normal(out, old, new, results))
+}
+
+/// Same as `write_ed`, but returns the script as a `String`.
+pub fn ed_diff(new: &[&str], results: &[DiffResult]) -> String {
+    write_to_string(|out| write_ed(out, new, results))
+}
+
+/// Same as `write_rcs`, but returns the text as a `String`.
+pub fn rcs_diff(new: &[&str], results: &[DiffResult]) -> String {
+    write_to_string(|out| write_rcs(out, new, results))
+}
+
+#[test]
+fn should_format_normal_diff() {
+    let old = ::split_lines("a\nb\nc");
+    let new = ::split_lines("a\nx\ny\nc\nd");
+    let text = normal_diff(&old, &new, &::diff(&old, &new));
+    assert_eq!(
+        text,
+        "2,3c2,5\n< b\n< c\n\\ No newline at end of file\n---\n> x\n> y\n> c\n> d\n\
+         \\ No newline at end of file\n"
+    );
+}
+
+#[test]
+fn should_format_ed_script() {
+    let old = ::split_lines("a\nb\nc\n");
+    let new = ::split_lines("a\n.\nb\n");
+    let text = ed_diff(&new, &::diff(&old, &new));
+    assert_eq!(text, "3d\n1a\n..\n.\ns/.//\n");
+}
+
+#[test]
+fn should_format_rcs_diff() {
+    let old = ::split_lines("a\nb\nc");
+    let new = ::split_lines("a\nx\ny\nc\nd");
+    let text = rcs_diff(&new, &::diff(&old, &new));
+    assert_eq!(text, "d2 2\na3 4\nx\ny\nc\nd");
+}
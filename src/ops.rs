@@ -0,0 +1,201 @@
+use std::ops::Range;
+
+use {common_affixes, reversed_body, DiffError, DiffLimits, DiffResult};
+
+/// A run of consecutive results of the same kind, as returned by `ops`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffOp {
+    /// `old[old]` equals `new[new]`.
+    Equal { old: Range<usize>, new: Range<usize> },
+    /// `old[old]` was removed; it sat right before `new[new_pos]`.
+    Delete { old: Range<usize>, new_pos: usize },
+    /// `new[new]` was added right before `old[old_pos]`.
+    Insert { old_pos: usize, new: Range<usize> },
+    /// `old[old]` was replaced with `new[new]`.
+    Replace { old: Range<usize>, new: Range<usize> },
+}
+
+impl DiffOp {
+    /// Returns the range of `old` covered by this operation.
+    pub fn old_range(&self) -> Range<usize> {
+        match *self {
+            DiffOp::Equal { ref old, .. } | DiffOp::Delete { ref old, .. } | DiffOp::Replace { ref old, .. } => {
+                old.clone()
+            }
+            DiffOp::Insert { old_pos, .. } => old_pos..old_pos,
+        }
+    }
+
+    /// Returns the range of `new` covered by this operation.
+    pub fn new_range(&self) -> Range<usize> {
+        match *self {
+            DiffOp::Equal { ref new, .. } | DiffOp::Insert { ref new, .. } | DiffOp::Replace { ref new, .. } => {
+                new.clone()
+            }
+            DiffOp::Delete { new_pos, .. } => new_pos..new_pos,
+        }
+    }
+}
+
+// Appends results one by one, merging them into the last operation when possible.
+struct OpsBuilder {
+    ops: Vec<DiffOp>,
+    old_pos: usize,
+    new_pos: usize,
+}
+
+impl OpsBuilder {
+    fn new() -> Self {
+        OpsBuilder {
+            ops: Vec::new(),
+            old_pos: 0,
+            new_pos: 0,
+        }
+    }
+
+    fn equal(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let (old_pos, new_pos) = (self.old_pos, self.new_pos);
+        self.old_pos += len;
+        self.new_pos += len;
+        if let Some(&mut DiffOp::Equal {
+            ref mut old,
+            ref mut new,
+        }) = self.ops.last_mut()
+        {
+            old.end += len;
+            new.end += len;
+            return;
+        }
+        self.ops.push(DiffOp::Equal {
+            old: old_pos..self.old_pos,
+            new: new_pos..self.new_pos,
+        });
+    }
+
+    fn remove(&mut self) {
+        let old_pos = self.old_pos;
+        self.old_pos += 1;
+        let op = match self.ops.pop() {
+            Some(DiffOp::Delete { old, new_pos }) => DiffOp::Delete {
+                old: old.start..self.old_pos,
+                new_pos,
+            },
+            Some(DiffOp::Insert { old_pos, new }) => DiffOp::Replace {
+                old: old_pos..self.old_pos,
+                new,
+            },
+            Some(DiffOp::Replace { old, new }) => DiffOp::Replace {
+                old: old.start..self.old_pos,
+                new,
+            },
+            last => {
+                self.ops.extend(last);
+                DiffOp::Delete {
+                    old: old_pos..self.old_pos,
+                    new_pos: self.new_pos,
+                }
+            }
+        };
+        self.ops.push(op);
+    }
+
+    fn add(&mut self) {
+        let new_pos = self.new_pos;
+        self.new_pos += 1;
+        let op = match self.ops.pop() {
+            Some(DiffOp::Insert { old_pos, new }) => DiffOp::Insert {
+                old_pos,
+                new: new.start..self.new_pos,
+            },
+            Some(DiffOp::Delete { old, new_pos }) => DiffOp::Replace {
+                old,
+                new: new_pos..self.new_pos,
+            },
+            Some(DiffOp::Replace { old, new }) => DiffOp::Replace {
+                old,
+                new: new.start..self.new_pos,
+            },
+            last => {
+                self.ops.extend(last);
+                DiffOp::Insert {
+                    old_pos: self.old_pos,
+                    new: new_pos..self.new_pos,
+                }
+            }
+        };
+        self.ops.push(op);
+    }
+
+    fn push(&mut self, result: &DiffResult) {
+        match *result {
+            DiffResult::Common(_) => self.equal(1),
+            DiffResult::Removed(_) => self.remove(),
+            DiffResult::Added(_) => self.add(),
+        }
+    }
+}
+
+/// Computes the same script as `diff`, grouped into runs.
+///
+/// The common prefix and suffix become a single `DiffOp::Equal` each without
+/// producing one result per element first.
+///
+/// # Panics
+///
+/// Panics if the edit graph cannot be allocated.
+pub fn ops<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    let (prefix_size, suffix_size) = common_affixes(old, new, &PartialEq::eq);
+    let sliced_old = &old[prefix_size..(old.len() - suffix_size)];
+    let sliced_new = &new[prefix_size..(new.len() - suffix_size)];
+    let body = reversed_body(
+        sliced_old,
+        sliced_new,
+        prefix_size,
+        &PartialEq::eq,
+        &DiffLimits::default(),
+        |_| Ok::<bool, DiffError>(true),
+    );
+    let (reversed, _) = body.expect("failed to compute diff");
+
+    let mut builder = OpsBuilder::new();
+    builder.equal(prefix_size);
+    for result in reversed.iter().rev() {
+        builder.push(result);
+    }
+    builder.equal(suffix_size);
+    builder.ops
+}
+
+/// Groups results produced by `diff` or one of its variants into runs.
+pub fn group_ops(results: &[DiffResult]) -> Vec<DiffOp> {
+    let mut builder = OpsBuilder::new();
+    for result in results {
+        builder.push(result);
+    }
+    builder.ops
+}
+
+#[test]
+fn should_group_results_into_ops() {
+    let old = vec!["a", "b", "c", "d", "e", "f"];
+    let new = vec!["a", "x", "y", "c", "e", "f", "g"];
+    let expected = vec![
+        DiffOp::Equal { old: 0..1, new: 0..1 },
+        DiffOp::Replace { old: 1..2, new: 1..3 },
+        DiffOp::Equal { old: 2..3, new: 3..4 },
+        DiffOp::Delete { old: 3..4, new_pos: 4 },
+        DiffOp::Equal { old: 4..6, new: 4..6 },
+        DiffOp::Insert { old_pos: 6, new: 6..7 },
+    ];
+    assert_eq!(ops(&old, &new), expected);
+    assert_eq!(group_ops(&::diff(&old, &new)), expected);
+}
+
+#[test]
+fn should_return_no_ops_for_empty_inputs() {
+    let empty: Vec<u8> = vec![];
+    assert_eq!(ops(&empty, &empty), vec![]);
+}
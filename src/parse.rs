@@ -0,0 +1,318 @@
+use std::error;
+use std::fmt;
+
+use {split_lines, ApplyError};
+
+/// A line of a `TextHunk`, keeping its terminator unless the patch marks it
+/// with `\ No newline at end of file`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkLine<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A `@@` hunk of a parsed unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextHunk<'a> {
+    /// 0-based index of the first line of `old` covered by the hunk, or the
+    /// index the insertion happens before when `old_len` is 0, as in `Hunk`.
+    pub old_start: usize,
+    pub old_len: usize,
+    /// Same as `old_start`, for `new`.
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<HunkLine<'a>>,
+}
+
+/// The changes to one file, as returned by `parse_patch`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilePatch<'a> {
+    /// Label of the `---` line without its timestamp, or the first path of a
+    /// `diff --git` line. Empty when the patch has neither.
+    pub old_path: &'a str,
+    /// Same as `old_path`, for the `+++` line.
+    pub new_path: &'a str,
+    pub hunks: Vec<TextHunk<'a>>,
+}
+
+/// Error returned by `parse_patch`. Line numbers are 1-based.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// A line starting with `@@` is not a valid hunk header.
+    InvalidHunkHeader { line: usize },
+    /// A line inside a hunk does not start with ` `, `-`, `+` or `\`.
+    UnexpectedLine { line: usize },
+    /// The input ends before the hunk starting at `line` is complete.
+    TruncatedHunk { line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::InvalidHunkHeader { line } => {
+                write!(f, "invalid hunk header at line {}", line)
+            }
+            ParseError::UnexpectedLine { line } => write!(f, "unexpected line {} in hunk", line),
+            ParseError::TruncatedHunk { line } => {
+                write!(f, "hunk starting at line {} is truncated", line)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+fn trim_newline(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+// Extracts the path of a `---`/`+++` line, dropping the timestamp.
+fn header_path(label: &str) -> &str {
+    let label = trim_newline(label);
+    label.split('\t').next().unwrap_or(label)
+}
+
+// Parses `start[,len]` into a 0-based start and a length.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, len) = match range.find(',') {
+        Some(i) => (range[..i].parse().ok()?, range[i + 1..].parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    };
+    let start: usize = start;
+    if len == 0 {
+        Some((start, 0))
+    } else {
+        Some((start.checked_sub(1)?, len))
+    }
+}
+
+// Parses `@@ -l,s +l,s @@` into an empty hunk.
+fn parse_hunk_header(line: &str) -> Option<TextHunk<'static>> {
+    let mut parts = trim_newline(line).split(' ');
+    if parts.next() != Some("@@") {
+        return None;
+    }
+    let (old_start, old_len) = parse_range(parts.next()?.strip_prefix('-')?)?;
+    let (new_start, new_len) = parse_range(parts.next()?.strip_prefix('+')?)?;
+    if parts.next() != Some("@@") {
+        return None;
+    }
+    Some(TextHunk {
+        old_start,
+        old_len,
+        new_start,
+        new_len,
+        lines: Vec::new(),
+    })
+}
+
+// Drops the terminator of the last line of `hunk`, after a `\` marker.
+fn strip_last_newline(hunk: &mut TextHunk) {
+    if let Some(line) = hunk.lines.last_mut() {
+        let text = match *line {
+            HunkLine::Context(ref mut text) | HunkLine::Removed(ref mut text) | HunkLine::Added(ref mut text) => text,
+        };
+        *text = text.strip_suffix('\n').unwrap_or(text);
+    }
+}
+
+/// Parses a unified diff, such as the output of `git diff` or `diff -u`, into
+/// one `FilePatch` per file.
+///
+/// Lines outside hunks that are not file headers, like `index` or mode lines of
+/// git patches, are ignored.
+pub fn parse_patch<'a>(text: &'a str) -> Result<Vec<FilePatch<'a>>, ParseError> {
+    let lines = split_lines(text);
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let rest = trim_newline(rest);
+            let (old_path, new_path) = match rest.find(" b/") {
+                Some(at) => (&rest[..at], &rest[at + 1..]),
+                None => (rest, rest),
+            };
+            files.push(FilePatch {
+                old_path,
+                new_path,
+                hunks: Vec::new(),
+            });
+        } else if line.starts_with("--- ") && lines.get(i + 1).map_or(false, |l| l.starts_with("+++ ")) {
+            // A git header already opened this file unless it has hunks.
+            match files.last_mut() {
+                Some(file) if file.hunks.is_empty() => {
+                    file.old_path = header_path(&line[4..]);
+                    file.new_path = header_path(&lines[i + 1][4..]);
+                }
+                _ => files.push(FilePatch {
+                    old_path: header_path(&line[4..]),
+                    new_path: header_path(&lines[i + 1][4..]),
+                    hunks: Vec::new(),
+                }),
+            }
+            i += 1;
+        } else if line.starts_with("@@") {
+            let header = i + 1;
+            let mut hunk = parse_hunk_header(line).ok_or(ParseError::InvalidHunkHeader { line: header })?;
+            let (mut old_left, mut new_left) = (hunk.old_len, hunk.new_len);
+            while old_left > 0 || new_left > 0 {
+                i += 1;
+                let line = *lines.get(i).ok_or(ParseError::TruncatedHunk { line: header })?;
+                let line = match line.as_bytes()[0] {
+                    b' ' => HunkLine::Context(&line[1..]),
+                    // Some tools strip the space of empty context lines.
+                    b'\n' | b'\r' => HunkLine::Context(line),
+                    b'-' => HunkLine::Removed(&line[1..]),
+                    b'+' => HunkLine::Added(&line[1..]),
+                    b'\\' => {
+                        strip_last_newline(&mut hunk);
+                        continue;
+                    }
+                    _ => return Err(ParseError::UnexpectedLine { line: i + 1 }),
+                };
+                match line {
+                    HunkLine::Context(_) if old_left > 0 && new_left > 0 => {
+                        old_left -= 1;
+                        new_left -= 1;
+                    }
+                    HunkLine::Removed(_) if old_left > 0 => old_left -= 1,
+                    HunkLine::Added(_) if new_left > 0 => new_left -= 1,
+                    _ => return Err(ParseError::UnexpectedLine { line: i + 1 }),
+                }
+                hunk.lines.push(line);
+            }
+            if lines.get(i + 1).map_or(false, |l| l.starts_with('\\')) {
+                strip_last_newline(&mut hunk);
+                i += 1;
+            }
+            if files.is_empty() {
+                files.push(FilePatch::default());
+            }
+            files.last_mut().expect("a file was pushed").hunks.push(hunk);
+        }
+        i += 1;
+    }
+    Ok(files)
+}
+
+impl<'a> FilePatch<'a> {
+    /// Applies the hunks to the lines `old`, which must keep their terminators
+    /// (see `split_lines`), and returns the lines of the patched file.
+    ///
+    /// Every hunk must match `old` exactly at its recorded position.
+    pub fn apply<'b>(&'b self, old: &[&'b str]) -> Result<Vec<&'b str>, ApplyError> {
+        let mut result = Vec::with_capacity(old.len());
+        let mut index = 0;
+        for hunk in &self.hunks {
+            if hunk.old_start < index || hunk.old_start > old.len() {
+                return Err(ApplyError::ContentMismatch { index: hunk.old_start });
+            }
+            result.extend_from_slice(&old[index..hunk.old_start]);
+            index = hunk.old_start;
+            for line in &hunk.lines {
+                match *line {
+                    HunkLine::Context(text) | HunkLine::Removed(text) => {
+                        if old.get(index) != Some(&text) {
+                            return Err(ApplyError::ContentMismatch { index });
+                        }
+                        if let HunkLine::Context(_) = *line {
+                            result.push(old[index]);
+                        }
+                        index += 1;
+                    }
+                    HunkLine::Added(text) => result.push(text),
+                }
+            }
+        }
+        result.extend_from_slice(&old[index..]);
+        Ok(result)
+    }
+}
+
+#[test]
+fn should_parse_git_patch() {
+    let text = "diff --git a/one b/one\n\
+                index 1234567..89abcde 100644\n\
+                --- a/one\n\
+                +++ b/one\n\
+                @@ -1,2 +1,2 @@ fn main\n\
+                \x20a\n\
+                -b\n\
+                \\ No newline at end of file\n\
+                +c\n\
+                diff --git a/old b/new\n\
+                similarity index 100%\n\
+                rename from old\n\
+                rename to new\n\
+                --- x\t2020-01-01\n\
+                +++ y\t2020-01-02\n\
+                @@ -0,0 +1 @@\n\
+                +z\n";
+    let files = parse_patch(text).unwrap();
+    assert_eq!(
+        files,
+        vec![
+            FilePatch {
+                old_path: "a/one",
+                new_path: "b/one",
+                hunks: vec![TextHunk {
+                    old_start: 0,
+                    old_len: 2,
+                    new_start: 0,
+                    new_len: 2,
+                    lines: vec![
+                        HunkLine::Context("a\n"),
+                        HunkLine::Removed("b"),
+                        HunkLine::Added("c\n"),
+                    ],
+                }],
+            },
+            FilePatch {
+                old_path: "x",
+                new_path: "y",
+                hunks: vec![TextHunk {
+                    old_start: 0,
+                    old_len: 0,
+                    new_start: 0,
+                    new_len: 1,
+                    lines: vec![HunkLine::Added("z\n")],
+                }],
+            },
+        ]
+    );
+    assert_eq!(files[0].apply(&["a\n", "b"]), Ok(vec!["a\n", "c\n"]));
+    assert_eq!(
+        files[0].apply(&["a\n", "b\n"]),
+        Err(ApplyError::ContentMismatch { index: 1 })
+    );
+}
+
+#[test]
+fn should_reject_malformed_hunks() {
+    assert_eq!(
+        parse_patch("--- a\n+++ b\n@@ -1 +1 @\n"),
+        Err(ParseError::InvalidHunkHeader { line: 3 })
+    );
+    assert_eq!(
+        parse_patch("--- a\n+++ b\n@@ -1,2 +1 @@\n-x\n"),
+        Err(ParseError::TruncatedHunk { line: 3 })
+    );
+    assert_eq!(
+        parse_patch("@@ -1 +1 @@\n*x\n"),
+        Err(ParseError::UnexpectedLine { line: 2 })
+    );
+}
+
+#[test]
+fn should_round_trip_unified_diff() {
+    let old = split_lines("a\nb\nc\nd\ne\nf\ng\nh\ni\n");
+    let new = split_lines("a\nB\nc\nd\ne\nf\ng\nh\ni\nj");
+    let text = ::unified_diff(&old, &new, &::diff(&old, &new), &::UnifiedFormat::default());
+    let files = parse_patch(&text).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].hunks.len(), 2);
+    assert_eq!(files[0].apply(&old), Ok(new));
+}
@@ -0,0 +1,504 @@
+use std::error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use algorithm::push_common;
+use {DiffElement, DiffResult};
+
+/// One step of a `Patch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp<T> {
+    /// Copy the next `n` elements of the base unchanged.
+    Keep(usize),
+    /// Skip the given elements of the base, which must match.
+    Delete(Vec<T>),
+    /// Emit the given elements.
+    Insert(Vec<T>),
+}
+
+/// An edit script that owns the elements it removes and inserts, so it can be
+/// stored and applied without the `new` sequence it was built from.
+///
+/// Kept elements are not stored, only a hash of each `Keep` run, which `apply`
+/// checks against the base. A patch can be taken apart with `old_len`,
+/// `new_len`, `ops` and `keep_hashes`, and put back together with `from_parts`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch<T> {
+    old_len: usize,
+    new_len: usize,
+    ops: Vec<PatchOp<T>>,
+    // One hash per `Keep` op, in order.
+    keep_hashes: Vec<u64>,
+}
+
+// FNV-1a over a fixed encoding: integers are fed little-endian, with `usize`
+// and `isize` (which also carry slice lengths) widened to 64 bits. Unlike with
+// `DefaultHasher`, a patch built on one platform then applies on any other, as
+// long as the `Hash` implementation of its elements stays the same.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+fn hash_run<T: Hash>(items: &[T]) -> u64 {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    items.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Error returned by `Patch::apply` when the base is not the one the patch was
+/// built from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyError {
+    /// The base has `actual` elements but the patch expects `expected`.
+    BaseLengthMismatch { expected: usize, actual: usize },
+    /// The base differs from the patch at `index`: either the element there
+    /// is not the one the patch removes, or the run of elements the patch keeps
+    /// from there does not hash the same.
+    ContentMismatch { index: usize },
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApplyError::BaseLengthMismatch { expected, actual } => write!(
+                f,
+                "patch expects a base of {} elements but got {}",
+                expected, actual
+            ),
+            ApplyError::ContentMismatch { index } => {
+                write!(f, "base does not match the patch at element {}", index)
+            }
+        }
+    }
+}
+
+impl error::Error for ApplyError {}
+
+impl<T: Clone + Hash> Patch<T> {
+    /// Builds a patch from `results`, computed between `old` and `new`.
+    ///
+    /// `T: Hash` is required because kept elements are not stored: their hashes
+    /// are what lets `apply` detect a base that changed outside the removed
+    /// elements. Types without `Hash`, such as `f64`, can be patched through a
+    /// wrapper hashing their bits.
+    pub fn new(old: &[T], new: &[T], results: &[DiffResult]) -> Self {
+        let mut ops: Vec<PatchOp<T>> = Vec::new();
+        for result in results {
+            match *result {
+                DiffResult::Common(_) => match ops.last_mut() {
+                    Some(&mut PatchOp::Keep(ref mut n)) => *n += 1,
+                    _ => ops.push(PatchOp::Keep(1)),
+                },
+                DiffResult::Removed(ref e) => {
+                    let item = old[e.old_index.expect("removed has old index")].clone();
+                    // Keep deletions ahead of insertions within a change.
+                    let mut at = ops.len();
+                    if let Some(&PatchOp::Insert(_)) = ops.last() {
+                        at -= 1;
+                    }
+                    match at.checked_sub(1).map(|i| &mut ops[i]) {
+                        Some(&mut PatchOp::Delete(ref mut items)) => items.push(item),
+                        _ => ops.insert(at, PatchOp::Delete(vec![item])),
+                    }
+                }
+                DiffResult::Added(ref e) => {
+                    let item = new[e.new_index.expect("added has new index")].clone();
+                    match ops.last_mut() {
+                        Some(&mut PatchOp::Insert(ref mut items)) => items.push(item),
+                        _ => ops.push(PatchOp::Insert(vec![item])),
+                    }
+                }
+            }
+        }
+        let mut keep_hashes = Vec::new();
+        let mut index = 0;
+        for op in &ops {
+            match *op {
+                PatchOp::Keep(n) => {
+                    keep_hashes.push(hash_run(&old[index..index + n]));
+                    index += n;
+                }
+                PatchOp::Delete(ref items) => index += items.len(),
+                PatchOp::Insert(_) => (),
+            }
+        }
+        Patch {
+            old_len: old.len(),
+            new_len: new.len(),
+            ops,
+            keep_hashes,
+        }
+    }
+
+    /// Rebuilds the `new` sequence from `old`, checking that `old` has the
+    /// expected length, contains the removed elements and that each kept run
+    /// hashes as it did in the original base.
+    pub fn apply(&self, old: &[T]) -> Result<Vec<T>, ApplyError>
+    where
+        T: PartialEq,
+    {
+        if old.len() != self.old_len {
+            return Err(ApplyError::BaseLengthMismatch {
+                expected: self.old_len,
+                actual: old.len(),
+            });
+        }
+        let mut result = Vec::with_capacity(self.new_len);
+        let mut index = 0;
+        let mut keep_hashes = self.keep_hashes.iter();
+        for op in &self.ops {
+            match *op {
+                PatchOp::Keep(n) => {
+                    let kept = &old[index..index + n];
+                    if keep_hashes.next() != Some(&hash_run(kept)) {
+                        return Err(ApplyError::ContentMismatch { index });
+                    }
+                    result.extend_from_slice(kept);
+                    index += n;
+                }
+                PatchOp::Delete(ref items) => {
+                    for item in items {
+                        if old[index] != *item {
+                            return Err(ApplyError::ContentMismatch { index });
+                        }
+                        index += 1;
+                    }
+                }
+                PatchOp::Insert(ref items) => result.extend_from_slice(items),
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl<T> Patch<T> {
+    /// Reassembles a patch from the parts returned by its accessors, e.g. after
+    /// storing them. Returns `None` if they are inconsistent: `keep_hashes` must
+    /// hold one hash per `Keep` op, and the ops must span `old_len` elements of
+    /// the base and `new_len` elements of the result.
+    pub fn from_parts(
+        old_len: usize,
+        new_len: usize,
+        ops: Vec<PatchOp<T>>,
+        keep_hashes: Vec<u64>,
+    ) -> Option<Self> {
+        let (mut old, mut new, mut keeps) = (0usize, 0usize, 0);
+        for op in &ops {
+            match *op {
+                PatchOp::Keep(n) => {
+                    old = old.checked_add(n)?;
+                    new = new.checked_add(n)?;
+                    keeps += 1;
+                }
+                PatchOp::Delete(ref items) => old = old.checked_add(items.len())?,
+                PatchOp::Insert(ref items) => new = new.checked_add(items.len())?,
+            }
+        }
+        if (old, new, keeps) != (old_len, new_len, keep_hashes.len()) {
+            return None;
+        }
+        Some(Patch {
+            old_len,
+            new_len,
+            ops,
+            keep_hashes,
+        })
+    }
+
+    /// Returns the patch turning the sequence this patch produces back into
+    /// the one it applies to.
+    pub fn invert(self) -> Self {
+        let mut ops: Vec<PatchOp<T>> = Vec::with_capacity(self.ops.len());
+        for op in self.ops {
+            match op {
+                PatchOp::Keep(n) => ops.push(PatchOp::Keep(n)),
+                PatchOp::Delete(items) => ops.push(PatchOp::Insert(items)),
+                PatchOp::Insert(items) => {
+                    // Keep deletions ahead of insertions within a change.
+                    let at = match ops.last() {
+                        Some(&PatchOp::Insert(_)) => ops.len() - 1,
+                        _ => ops.len(),
+                    };
+                    ops.insert(at, PatchOp::Delete(items));
+                }
+            }
+        }
+        // Kept runs are the same on both sides.
+        Patch {
+            old_len: self.new_len,
+            new_len: self.old_len,
+            ops,
+            keep_hashes: self.keep_hashes,
+        }
+    }
+
+    /// Length of the sequence the patch applies to.
+    pub fn old_len(&self) -> usize {
+        self.old_len
+    }
+
+    /// Length of the sequence the patch produces.
+    pub fn new_len(&self) -> usize {
+        self.new_len
+    }
+
+    pub fn ops(&self) -> &[PatchOp<T>] {
+        &self.ops
+    }
+
+    /// Hashes of the kept runs, one per `Keep` op in `ops`.
+    pub fn keep_hashes(&self) -> &[u64] {
+        &self.keep_hashes
+    }
+}
+
+/// Turns an old→new edit script into the new→old one, swapping `Added` with
+/// `Removed` and `old_index` with `new_index`.
+///
+/// Within each change, the removals are listed before the additions.
+pub fn invert(results: &[DiffResult]) -> Vec<DiffResult> {
+    let mut inverted = Vec::with_capacity(results.len());
+    let mut added = Vec::new();
+    for result in results {
+        match *result {
+            DiffResult::Common(ref e) => {
+                inverted.append(&mut added);
+                inverted.push(DiffResult::Common(DiffElement {
+                    old_index: e.new_index,
+                    new_index: e.old_index,
+                }));
+            }
+            DiffResult::Removed(ref e) => added.push(DiffResult::Added(DiffElement {
+                old_index: None,
+                new_index: e.old_index,
+            })),
+            DiffResult::Added(ref e) => inverted.push(DiffResult::Removed(DiffElement {
+                old_index: e.new_index,
+                new_index: None,
+            })),
+        }
+    }
+    inverted.append(&mut added);
+    inverted
+}
+
+// Appends the removal of `old` followed by the addition of `new`.
+fn push_change(result: &mut Vec<DiffResult>, old: Range<usize>, new: Range<usize>) {
+    result.extend(old.map(|i| {
+        DiffResult::Removed(DiffElement {
+            old_index: Some(i),
+            new_index: None,
+        })
+    }));
+    result.extend(new.map(|i| {
+        DiffResult::Added(DiffElement {
+            old_index: None,
+            new_index: Some(i),
+        })
+    }));
+}
+
+/// Combines `first`, an A→B edit script, with `second`, a B→C one, into an A→C
+/// script without looking at the elements.
+///
+/// An element of A is kept when both scripts keep it through B; everything else
+/// is removed or added, removals first within each change. The result is valid
+/// but not necessarily minimal.
+///
+/// # Panics
+///
+/// Panics if `second` does not apply to the sequence `first` produces.
+pub fn compose(first: &[DiffResult], second: &[DiffResult]) -> Vec<DiffResult> {
+    // For each element of B, the element of A it was kept from.
+    let mut middle = Vec::with_capacity(first.len());
+    let mut old_len = 0;
+    for result in first {
+        match *result {
+            DiffResult::Removed(_) => old_len += 1,
+            DiffResult::Common(ref e) => {
+                old_len += 1;
+                middle.push(e.old_index);
+            }
+            DiffResult::Added(_) => middle.push(None),
+        }
+    }
+    let mut composed = Vec::with_capacity(first.len() + second.len());
+    let (mut old_index, mut new_index) = (0, 0);
+    let (mut middle_len, mut new_len) = (0, 0);
+    for result in second {
+        match *result {
+            DiffResult::Removed(_) => middle_len += 1,
+            DiffResult::Added(_) => new_len += 1,
+            DiffResult::Common(ref e) => {
+                middle_len += 1;
+                new_len += 1;
+                let kept = middle
+                    .get(e.old_index.expect("common has old index"))
+                    .expect("second script applies to the output of the first");
+                if let Some(old) = *kept {
+                    let new = e.new_index.expect("common has new index");
+                    push_change(&mut composed, old_index..old, new_index..new);
+                    push_common(&mut composed, old, new, 1);
+                    old_index = old + 1;
+                    new_index = new + 1;
+                }
+            }
+        }
+    }
+    assert_eq!(
+        middle_len,
+        middle.len(),
+        "second script applies to the output of the first"
+    );
+    push_change(&mut composed, old_index..old_len, new_index..new_len);
+    composed
+}
+
+#[test]
+fn should_apply_patch() {
+    let old = vec!["a", "b", "c", "d"];
+    let new = vec!["a", "x", "c", "d", "e"];
+    let patch = Patch::new(&old, &new, &::diff(&old, &new));
+    assert_eq!(
+        patch.ops(),
+        &[
+            PatchOp::Keep(1),
+            PatchOp::Delete(vec!["b"]),
+            PatchOp::Insert(vec!["x"]),
+            PatchOp::Keep(2),
+            PatchOp::Insert(vec!["e"]),
+        ]
+    );
+    assert_eq!(patch.apply(&old), Ok(new));
+}
+
+#[test]
+fn should_reject_mismatched_base() {
+    let old = vec![1, 2, 3];
+    let new = vec![1, 3];
+    let patch = Patch::new(&old, &new, &::diff(&old, &new));
+    assert_eq!(
+        patch.apply(&[1, 2]),
+        Err(ApplyError::BaseLengthMismatch {
+            expected: 3,
+            actual: 2
+        })
+    );
+    assert_eq!(
+        patch.apply(&[1, 4, 3]),
+        Err(ApplyError::ContentMismatch { index: 1 })
+    );
+    assert_eq!(
+        patch.apply(&[1, 2, 5]),
+        Err(ApplyError::ContentMismatch { index: 2 })
+    );
+}
+
+#[test]
+fn should_rebuild_patch_from_parts() {
+    let old = vec!["a", "b", "c"];
+    let new = vec!["a", "x", "c"];
+    let patch = Patch::new(&old, &new, &::diff(&old, &new));
+    let ops = patch.ops().to_vec();
+    let hashes = patch.keep_hashes().to_vec();
+    assert_eq!(Patch::from_parts(3, 3, ops.clone(), hashes.clone()), Some(patch));
+    assert_eq!(Patch::from_parts(4, 3, ops.clone(), hashes.clone()), None);
+    assert_eq!(Patch::from_parts(3, 3, ops, hashes[1..].to_vec()), None);
+}
+
+#[test]
+fn should_hash_kept_runs_portably() {
+    // FNV-1a of the little-endian 64-bit length followed by the elements.
+    assert_eq!(hash_run(&[1u32, 2]), 14_351_894_696_384_450_980);
+    assert_eq!(hash_run(&[1usize, 2]), 4_768_729_763_816_426_308);
+    assert_eq!(hash_run(&["a", "b"]), 12_675_061_419_070_943_856);
+}
+
+#[test]
+fn should_invert_results() {
+    let old = vec!["a", "b", "c"];
+    let new = vec!["x", "a", "c", "y"];
+    assert_eq!(invert(&::diff(&old, &new)), ::diff(&new, &old));
+}
+
+#[test]
+fn should_invert_patch() {
+    let old = vec![1, 2, 3, 4];
+    let new = vec![5, 2, 4, 6];
+    let patch = Patch::new(&old, &new, &::diff(&old, &new)).invert();
+    assert_eq!(patch.old_len(), 4);
+    assert_eq!(
+        patch.ops(),
+        &[
+            PatchOp::Delete(vec![5]),
+            PatchOp::Insert(vec![1]),
+            PatchOp::Keep(1),
+            PatchOp::Insert(vec![3]),
+            PatchOp::Keep(1),
+            PatchOp::Delete(vec![6]),
+        ]
+    );
+    assert_eq!(patch.apply(&new), Ok(old));
+}
+
+#[test]
+fn should_compose_scripts() {
+    let a = vec!["a", "b", "c", "d"];
+    let b = vec!["a", "x", "c", "d"];
+    let c = vec!["c", "d", "y"];
+    let composed = compose(&::diff(&a, &b), &::diff(&b, &c));
+    assert_eq!(composed, ::diff(&a, &c));
+    let patch = Patch::new(&a, &c, &composed);
+    assert_eq!(patch.apply(&a), Ok(c));
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use algorithm::{extend_shifted, push_common};
+use {common_affixes, diff_by, DiffResult};
+
+// Occurrences of a token in the old and new slices, with the index of the last one.
+#[derive(Default)]
+struct Occurrence {
+    old_count: usize,
+    old_index: usize,
+    new_count: usize,
+    new_index: usize,
+}
+
+pub(crate) fn patience(old: &[u32], new: &[u32]) -> Vec<DiffResult> {
+    let mut result = Vec::with_capacity(old.len() + new.len());
+    patience_range(old, new, 0, 0, &mut result);
+    result
+}
+
+fn patience_range(old: &[u32], new: &[u32], old_offset: usize, new_offset: usize, result: &mut Vec<DiffResult>) {
+    let (prefix_size, suffix_size) = common_affixes(old, new, &PartialEq::eq);
+    push_common(result, old_offset, new_offset, prefix_size);
+
+    let sliced_old = &old[prefix_size..(old.len() - suffix_size)];
+    let sliced_new = &new[prefix_size..(new.len() - suffix_size)];
+    let old_start = old_offset + prefix_size;
+    let new_start = new_offset + prefix_size;
+    let anchors = unique_anchors(sliced_old, sliced_new);
+    if anchors.is_empty() {
+        let results = diff_by(sliced_old, sliced_new, PartialEq::eq);
+        extend_shifted(result, results, old_start, new_start);
+    } else {
+        let (mut o, mut n) = (0, 0);
+        for (a, b) in anchors {
+            patience_range(&sliced_old[o..a], &sliced_new[n..b], old_start + o, new_start + n, result);
+            push_common(result, old_start + a, new_start + b, 1);
+            o = a + 1;
+            n = b + 1;
+        }
+        patience_range(&sliced_old[o..], &sliced_new[n..], old_start + o, new_start + n, result);
+    }
+
+    push_common(
+        result,
+        old_offset + old.len() - suffix_size,
+        new_offset + new.len() - suffix_size,
+        suffix_size,
+    );
+}
+
+// Returns the `(old, new)` index pairs of tokens occurring exactly once in both
+// slices which form the longest sequence increasing in both indices.
+fn unique_anchors(old: &[u32], new: &[u32]) -> Vec<(usize, usize)> {
+    let mut occurrences: HashMap<u32, Occurrence> = HashMap::new();
+    for (i, token) in old.iter().enumerate() {
+        let o = occurrences.entry(*token).or_default();
+        o.old_count += 1;
+        o.old_index = i;
+    }
+    for (i, token) in new.iter().enumerate() {
+        if let Some(o) = occurrences.get_mut(token) {
+            o.new_count += 1;
+            o.new_index = i;
+        }
+    }
+    let mut candidates: Vec<(usize, usize)> = occurrences
+        .values()
+        .filter(|o| o.old_count == 1 && o.new_count == 1)
+        .map(|o| (o.old_index, o.new_index))
+        .collect();
+    candidates.sort();
+    longest_increasing(&candidates)
+}
+
+// Patience sorting: finds the longest subsequence of `candidates`, which are
+// sorted by old index, whose new indices are increasing as well.
+fn longest_increasing(candidates: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // `tails[l]` is the candidate ending the best known sequence of length `l + 1`.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = Vec::with_capacity(candidates.len());
+    for (i, &(_, new_index)) in candidates.iter().enumerate() {
+        let pos = match tails.binary_search_by(|&t| candidates[t].1.cmp(&new_index)) {
+            Ok(pos) | Err(pos) => pos,
+        };
+        prev.push(if pos > 0 { Some(tails[pos - 1]) } else { None });
+        if pos == tails.len() {
+            tails.push(i);
+        } else {
+            tails[pos] = i;
+        }
+    }
+    let mut anchors = Vec::with_capacity(tails.len());
+    let mut current = tails.last().cloned();
+    while let Some(i) = current {
+        anchors.push(candidates[i]);
+        current = prev[i];
+    }
+    anchors.reverse();
+    anchors
+}
+
+#[test]
+fn should_find_longest_increasing_anchors() {
+    let candidates = vec![(0, 3), (1, 0), (2, 4), (3, 1), (4, 2)];
+    assert_eq!(longest_increasing(&candidates), vec![(1, 0), (3, 1), (4, 2)]);
+}
+
+#[test]
+fn should_anchor_on_unique_lines() {
+    let old = vec!["fn a() {", "  1", "}", "", "fn b() {", "  2", "}"];
+    let new = vec!["fn b() {", "  2", "}", "", "fn a() {", "  1", "}"];
+    let result = ::diff_with_algorithm(&old, &new, ::Algorithm::Patience);
+    let common: Vec<(usize, usize)> = result
+        .iter()
+        .filter_map(|r| match r {
+            DiffResult::Common(e) => Some((e.old_index.unwrap(), e.new_index.unwrap())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(common, vec![(4, 0), (5, 1), (6, 6)]);
+}
@@ -0,0 +1,175 @@
+use std::io::{self, Write};
+
+use ops::group_ops;
+use unified::write_to_string;
+use width::char_width;
+use {DiffOp, DiffResult};
+
+/// Layout used by `write_side_by_side`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SideBySideFormat {
+    /// Total width of a row in columns, including the gutter.
+    pub width: usize,
+    /// Tab stops are placed every `tab_size` columns.
+    pub tab_size: usize,
+    /// Wraps lines longer than a column onto extra rows instead of truncating.
+    pub wrap: bool,
+}
+
+impl Default for SideBySideFormat {
+    fn default() -> Self {
+        SideBySideFormat {
+            width: 130,
+            tab_size: 8,
+            wrap: false,
+        }
+    }
+}
+
+// Drops the line terminator and expands tabs to spaces.
+fn expand_tabs(line: &str, tab_size: usize) -> String {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' && tab_size > 0 {
+            let spaces = tab_size - column % tab_size;
+            expanded.extend((0..spaces).map(|_| ' '));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += char_width(c);
+        }
+    }
+    expanded
+}
+
+// Splits `text` into pieces of at most `width` columns. Only the first piece is
+// returned unless `wrap` is set. A wide character never straddles two pieces.
+fn fit(text: &str, width: usize, wrap: bool) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut used = 0;
+    for c in text.chars() {
+        let w = char_width(c);
+        if used + w > width {
+            if !wrap || w > width {
+                break;
+            }
+            pieces.push(String::new());
+            used = 0;
+        }
+        pieces.last_mut().expect("pieces are never empty").push(c);
+        used += w;
+    }
+    pieces
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let used: usize = text.chars().map(char_width).sum();
+    let mut padded = String::from(text);
+    padded.extend((used..width).map(|_| ' '));
+    padded
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, as two columns
+/// like `diff -y` or `sdiff` do.
+///
+/// Removed and added runs are paired on the same rows and marked with `|`,
+/// leftover removed lines with `<` and leftover added lines with `>`. Column
+/// widths account for tabs and wide characters.
+pub fn write_side_by_side<W: Write>(
+    out: &mut W,
+    old: &[&str],
+    new: &[&str],
+    results: &[DiffResult],
+    format: &SideBySideFormat,
+) -> io::Result<()> {
+    let column = format.width.saturating_sub(3) / 2;
+    for op in group_ops(results) {
+        let old_range = op.old_range();
+        let new_range = op.new_range();
+        let rows = if let DiffOp::Equal { .. } = op {
+            old_range.len()
+        } else {
+            old_range.len().max(new_range.len())
+        };
+        for row in 0..rows {
+            let left = old_range.clone().nth(row).map(|i| expand_tabs(old[i], format.tab_size));
+            let right = new_range.clone().nth(row).map(|i| expand_tabs(new[i], format.tab_size));
+            let marker = match (&op, &left, &right) {
+                (&DiffOp::Equal { .. }, _, _) => ' ',
+                (_, &Some(_), &Some(_)) => '|',
+                (_, &Some(_), &None) => '<',
+                _ => '>',
+            };
+            let left = fit(left.as_ref().map_or("", |s| s), column, format.wrap);
+            let right = fit(right.as_ref().map_or("", |s| s), column, format.wrap);
+            for i in 0..left.len().max(right.len()) {
+                let l = left.get(i).map_or("", |s| s);
+                let r = right.get(i).map_or("", |s| s);
+                let row = format!("{} {} {}", pad(l, column), marker, r);
+                writeln!(out, "{}", row.trim_end())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write_side_by_side`, but returns the text as a `String`.
+pub fn side_by_side(old: &[&str], new: &[&str], results: &[DiffResult], format: &SideBySideFormat) -> String {
+    write_to_string(|out| write_side_by_side(out, old, new, results, format))
+}
+
+#[test]
+fn should_render_side_by_side() {
+    let old = ::split_lines("a\nb\nc\nd\n");
+    let new = ::split_lines("a\nx\ny\nc\n");
+    let format = SideBySideFormat {
+        width: 13,
+        ..SideBySideFormat::default()
+    };
+    let text = side_by_side(&old, &new, &::diff(&old, &new), &format);
+    assert_eq!(text, "a       a\nb     | x\n      > y\nc       c\nd     <\n");
+}
+
+#[test]
+fn should_align_tabs_and_wide_characters() {
+    let old = vec!["\tx\n", "日本語テキスト\n"];
+    let new = vec!["\tx\n", "text\n"];
+    let format = SideBySideFormat {
+        width: 23,
+        tab_size: 4,
+        wrap: false,
+    };
+    let text = side_by_side(&old, &new, &::diff(&old, &new), &format);
+    assert_eq!(text, "    x            x\n日本語テキ | text\n");
+}
+
+#[test]
+fn should_wrap_long_lines() {
+    let old = vec!["abcdefgh"];
+    let new = vec!["abc"];
+    let format = SideBySideFormat {
+        width: 11,
+        tab_size: 8,
+        wrap: true,
+    };
+    let text = side_by_side(&old, &new, &::diff(&old, &new), &format);
+    assert_eq!(text, "abcd | abc\nefgh |\n");
+}
+
+#[test]
+fn should_align_emoji_and_combining_marks() {
+    let old = vec!["ab\n", "🚀🚀\n", "✅ok\n", "e\u{301}\u{20d7}x\n"];
+    let new = vec!["xx\n", "yy\n", "zz\n", "ww\n"];
+    let format = SideBySideFormat {
+        width: 13,
+        ..SideBySideFormat::default()
+    };
+    let text = side_by_side(&old, &new, &::diff(&old, &new), &format);
+    assert_eq!(
+        text,
+        "ab    | xx\n🚀🚀  | yy\n✅ok  | zz\ne\u{301}\u{20d7}x    | ww\n"
+    );
+}
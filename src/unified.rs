@@ -0,0 +1,145 @@
+use std::io::{self, Write};
+
+use hunk::hunks;
+use {DiffOp, DiffResult};
+
+/// Labels and context radius used by `write_unified`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnifiedFormat<'a> {
+    /// Written after `--- `, e.g. `"a/src/lib.rs"` or a file name followed by a
+    /// tab and a timestamp.
+    pub old_label: &'a str,
+    /// Written after `+++ `.
+    pub new_label: &'a str,
+    /// Number of common lines shown around each change.
+    pub context: usize,
+}
+
+impl<'a> Default for UnifiedFormat<'a> {
+    fn default() -> Self {
+        UnifiedFormat {
+            old_label: "a",
+            new_label: "b",
+            context: 3,
+        }
+    }
+}
+
+/// Splits `text` into lines, keeping the `\n` terminators.
+///
+/// This is the line representation expected by the text formatters: a last line
+/// without terminator is reported with `\ No newline at end of file`.
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+// Writes `line` behind `prefix`, terminating it if it lacks a newline.
+pub(crate) fn write_line<W: Write>(out: &mut W, prefix: &str, line: &str) -> io::Result<()> {
+    out.write_all(prefix.as_bytes())?;
+    out.write_all(line.as_bytes())?;
+    if !line.ends_with('\n') {
+        out.write_all(b"\n\\ No newline at end of file\n")?;
+    }
+    Ok(())
+}
+
+// Formats a hunk range the way GNU diff does: `start` alone for one line,
+// `start,len` otherwise, and the line before the range for empty ranges.
+pub(crate) fn unified_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Writes `results`, computed between the lines `old` and `new`, in unified
+/// format, byte-compatible with GNU `diff -u`.
+///
+/// Lines are expected to keep their terminators, see `split_lines`. Nothing is
+/// written when there are no changes.
+pub fn write_unified<W: Write>(
+    out: &mut W,
+    old: &[&str],
+    new: &[&str],
+    results: &[DiffResult],
+    format: &UnifiedFormat,
+) -> io::Result<()> {
+    let hunks = hunks(results, format.context);
+    if hunks.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "--- {}", format.old_label)?;
+    writeln!(out, "+++ {}", format.new_label)?;
+    for hunk in hunks {
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            unified_range(hunk.old_start, hunk.old_len),
+            unified_range(hunk.new_start, hunk.new_len)
+        )?;
+        for op in hunk.ops {
+            if let DiffOp::Equal { old: range, .. } = op {
+                for line in &old[range] {
+                    write_line(out, " ", line)?;
+                }
+                continue;
+            }
+            for line in &old[op.old_range()] {
+                write_line(out, "-", line)?;
+            }
+            for line in &new[op.new_range()] {
+                write_line(out, "+", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Runs `write` against an in-memory buffer and returns what it wrote, for the
+// `String` returning counterparts of the `write_*` functions.
+pub(crate) fn write_to_string<F: FnOnce(&mut Vec<u8>) -> io::Result<()>>(write: F) -> String {
+    let mut out = Vec::new();
+    write(&mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("lines are valid UTF-8")
+}
+
+/// Same as `write_unified`, but returns the text as a `String`.
+pub fn unified_diff(old: &[&str], new: &[&str], results: &[DiffResult], format: &UnifiedFormat) -> String {
+    write_to_string(|out| write_unified(out, old, new, results, format))
+}
+
+#[test]
+fn should_format_unified_diff() {
+    let old = split_lines("a\nb\nc\nd\n");
+    let new = split_lines("a\nx\nc\nd\ne\n");
+    let format = UnifiedFormat {
+        old_label: "old.txt",
+        new_label: "new.txt",
+        context: 1,
+    };
+    let text = unified_diff(&old, &new, &::diff(&old, &new), &format);
+    assert_eq!(
+        text,
+        "--- old.txt\n+++ new.txt\n@@ -1,4 +1,5 @@\n a\n-b\n+x\n c\n d\n+e\n"
+    );
+}
+
+#[test]
+fn should_mark_missing_newline() {
+    let old = split_lines("a\nb");
+    let new = split_lines("a\nb\n");
+    let text = unified_diff(&old, &new, &::diff(&old, &new), &UnifiedFormat::default());
+    assert_eq!(
+        text,
+        "--- a\n+++ b\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+    );
+}
+
+#[test]
+fn should_format_empty_ranges() {
+    let old: Vec<&str> = vec![];
+    let new = split_lines("a\n");
+    let text = unified_diff(&old, &new, &::diff(&old, &new), &UnifiedFormat::default());
+    assert_eq!(text, "--- a\n+++ b\n@@ -0,0 +1 @@\n+a\n");
+}